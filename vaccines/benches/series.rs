@@ -0,0 +1,159 @@
+//! Compares `TimeSeries` with `dense::DenseSeries` on data the size of the SSI files split by
+//! municipality and age band. Run with `cargo bench`.
+
+use chrono::NaiveDate;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use klima::table::dense::DenseSeries;
+use klima::table::{Fill, TimeSeries, TimeSeriesGroup};
+
+const MUNICIPALITIES: usize = 98;
+const AGE_BANDS: usize = 10;
+/// From the first case until mid 2021.
+const DAYS: i64 = 480;
+
+/// A series per municipality and age band, with about one day in twenty left out like the
+/// days without cases in the SSI files.
+fn group() -> TimeSeriesGroup {
+    let start = NaiveDate::from_ymd(2020, 2, 26);
+    let mut seed: u64 = 1;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        seed >> 33
+    };
+    let series = (0..MUNICIPALITIES * AGE_BANDS)
+        .map(|n| {
+            let tags = im::OrdSet::unit(format!("{}", n));
+            let data = (0..DAYS)
+                .filter_map(|day| {
+                    let value = (random() % 100) as i64;
+                    let date = start + chrono::Duration::days(day);
+                    Some((date, value)).filter(|_| value % 20 != 0)
+                })
+                .collect();
+            TimeSeries::new(tags, data).with_fill(Fill::Zero)
+        })
+        .collect();
+    TimeSeriesGroup::new(series)
+}
+
+fn final_date() -> NaiveDate {
+    NaiveDate::from_ymd(2020, 2, 26) + chrono::Duration::days(DAYS)
+}
+
+fn series(c: &mut Criterion) {
+    let map = group().series().to_vec();
+    let dense = group().dense();
+    let start = NaiveDate::from_ymd(2020, 2, 1);
+
+    c.bench_function("map/accumulative", |b| {
+        b.iter_batched(
+            || map.clone(),
+            |all| {
+                let acc: Vec<_> = all
+                    .into_iter()
+                    .map(|ts| ts.accumulative(final_date()))
+                    .collect();
+                acc
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("dense/accumulative", |b| {
+        b.iter_batched(
+            || dense.clone(),
+            |all| {
+                let acc: Vec<_> = all
+                    .into_iter()
+                    .map(|ts| ts.accumulative(final_date()))
+                    .collect();
+                acc
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("map/diff", |b| {
+        b.iter_batched(
+            || map.clone(),
+            |all| all.into_iter().map(TimeSeries::diff).collect::<Vec<_>>(),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("dense/diff", |b| {
+        b.iter_batched(
+            || dense.clone(),
+            |all| all.into_iter().map(DenseSeries::diff).collect::<Vec<_>>(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("map/prepend", |b| {
+        b.iter_batched(
+            || map.clone(),
+            |all| {
+                let step = chrono::Duration::days(1);
+                let prepended: Vec<_> = all
+                    .into_iter()
+                    .map(|ts| ts.prepend(0, start, step))
+                    .collect();
+                prepended
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("dense/prepend", |b| {
+        b.iter_batched(
+            || dense.clone(),
+            |all| {
+                all.into_iter()
+                    .map(|ts| ts.prepend(0, start))
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("map/total", |b| {
+        b.iter_batched(
+            || map.clone(),
+            |all| all.into_iter().reduce(|a, b| a + b),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("dense/total", |b| {
+        b.iter_batched(
+            || dense.clone(),
+            |all| all.into_iter().reduce(|a, b| a + b),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let group = group();
+    c.bench_function("map/xs", |b| b.iter(|| black_box(&group).xs()));
+    c.bench_function("dense/xs", |b| {
+        b.iter(|| {
+            black_box(&dense)
+                .iter()
+                .flat_map(|ts| ts.dates())
+                .collect::<im::OrdSet<NaiveDate>>()
+        })
+    });
+
+    c.bench_function("convert/to_dense", |b| b.iter(|| black_box(&group).dense()));
+    c.bench_function("convert/from_dense", |b| {
+        b.iter_batched(
+            || dense.clone(),
+            TimeSeriesGroup::from_dense,
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = series
+}
+criterion_main!(benches);
@@ -0,0 +1,346 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub const SSI_URL: &str = "https://covid19.ssi.dk";
+
+/// A download page on the SSI site and the file name prefix of the archives it links to.
+pub struct Dataset {
+    pub page: &'static str,
+    pub prefix: &'static str,
+}
+
+pub const VACCINATIONS: Dataset = Dataset {
+    page: "overvagningsdata/download-fil-med-vaccinationsdata",
+    prefix: "vaccinationsdata-",
+};
+
+pub const SURVEILLANCE: Dataset = Dataset {
+    page: "overvagningsdata/download-fil-med-overvaagningdata",
+    prefix: "overvaagningsdata-",
+};
+
+#[derive(Debug)]
+pub enum FetchError {
+    BadUrl(String),
+    Http(reqwest::Error),
+    Status { url: String, status: StatusCode },
+    NoLink { page: String, prefix: String },
+    Io { file: String, cause: std::io::Error },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::BadUrl(url) => write!(f, "invalid url {}", url),
+            FetchError::Http(e) => write!(f, "{}", e),
+            FetchError::Status { url, status } => write!(f, "{} returned {}", url, status),
+            FetchError::NoLink { page, prefix } => {
+                write!(f, "no link to {}* on {}", prefix, page)
+            }
+            FetchError::Io { file, cause } => write!(f, "could not write {}: {}", file, cause),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Http(e)
+    }
+}
+
+/// What we know about a downloaded archive, stored next to it as `<archive>.json`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Download {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub sha256: String,
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// False if the server said our copy is still current.
+    #[serde(skip)]
+    pub updated: bool,
+}
+
+impl Download {
+    /// The day SSI published the archive, from `Last-Modified`, or today if the server did not
+    /// say.
+    pub fn published(&self) -> NaiveDate {
+        self.last_modified
+            .as_deref()
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.naive_utc().date())
+            .unwrap_or_else(|| Utc::now().naive_utc().date())
+    }
+
+    fn meta_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".json");
+        PathBuf::from(name)
+    }
+
+    /// The recorded download of `path`, unless the file is gone or no longer has the
+    /// recorded checksum, so it is downloaded again in full.
+    fn load(path: &Path) -> Option<Download> {
+        let meta = std::fs::read(Self::meta_path(path)).ok()?;
+        let bytes = std::fs::read(path).ok()?;
+        let download: Download = serde_json::from_slice(&meta).ok()?;
+        if format!("{:x}", Sha256::digest(&bytes)) != download.sha256 {
+            return None;
+        }
+        Some(Download {
+            path: path.to_path_buf(),
+            ..download
+        })
+    }
+
+    fn save(&self) -> Result<(), FetchError> {
+        let meta_path = Self::meta_path(&self.path);
+        std::fs::write(&meta_path, serde_json::to_vec_pretty(self).unwrap()).map_err(|cause| {
+            FetchError::Io {
+                file: meta_path.display().to_string(),
+                cause,
+            }
+        })
+    }
+}
+
+/// Downloads SSI archives into a directory, e.g. the data directory given to `DataDir`.
+pub struct Fetcher {
+    base: Url,
+    client: Client,
+    target: PathBuf,
+}
+
+impl Fetcher {
+    /// `base` is the SSI site, normally `SSI_URL`, but can point at a local server.
+    pub fn new(base: &str, target: impl Into<PathBuf>) -> Result<Self, FetchError> {
+        // Pages are joined onto the base, so make sure it is treated as a directory.
+        let dir = format!("{}/", base.trim_end_matches('/'));
+        Ok(Fetcher {
+            base: Url::parse(&dir).map_err(|_| FetchError::BadUrl(base.to_string()))?,
+            client: Client::new(),
+            target: target.into(),
+        })
+    }
+
+    /// The first archive linked from the dataset's download page, which SSI keeps newest first.
+    pub fn latest_link(&self, dataset: &Dataset) -> Result<Url, FetchError> {
+        let page = self.base.join(dataset.page).unwrap();
+        let response = self.client.get(page.clone()).send()?;
+        if !response.status().is_success() {
+            return Err(FetchError::Status {
+                url: page.to_string(),
+                status: response.status(),
+            });
+        }
+        let html = response.text()?;
+
+        let link = links(&html)
+            .filter_map(|href| page.join(href).ok())
+            .find(|url| file_name(url).is_some_and(|name| name.starts_with(dataset.prefix)));
+        link.ok_or_else(|| FetchError::NoLink {
+            page: page.to_string(),
+            prefix: dataset.prefix.to_string(),
+        })
+    }
+
+    /// Download `url` unless our copy is current, and record its checksum.
+    pub fn download(&self, url: &Url) -> Result<Download, FetchError> {
+        let name = file_name(url).unwrap_or("download");
+        let name = if name.ends_with(".zip") {
+            name.to_string()
+        } else {
+            format!("{}.zip", name)
+        };
+        let path = self.target.join(name);
+
+        let previous = Download::load(&path).filter(|d| d.url == url.as_str());
+        let mut request = self.client.get(url.clone());
+        if let Some(previous) = &previous {
+            if let Some(etag) = &previous.etag {
+                request = request.header(IF_NONE_MATCH, etag.as_str());
+            }
+            if let Some(last_modified) = &previous.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified.as_str());
+            }
+        }
+
+        let response = request.send()?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(previous) = previous {
+                return Ok(Download {
+                    updated: false,
+                    ..previous
+                });
+            }
+        }
+        if !response.status().is_success() {
+            return Err(FetchError::Status {
+                url: url.to_string(),
+                status: response.status(),
+            });
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let bytes = response.bytes()?;
+
+        std::fs::write(&path, &bytes).map_err(|cause| FetchError::Io {
+            file: path.display().to_string(),
+            cause,
+        })?;
+
+        let download = Download {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            sha256: format!("{:x}", Sha256::digest(&bytes)),
+            path,
+            updated: true,
+        };
+        download.save()?;
+        Ok(download)
+    }
+
+    pub fn fetch(&self, dataset: &Dataset) -> Result<Download, FetchError> {
+        self.download(&self.latest_link(dataset)?)
+    }
+}
+
+fn file_name(url: &Url) -> Option<&str> {
+    url.path_segments().and_then(|mut s| s.next_back())
+}
+
+/// The `href` attributes of an HTML page.
+fn links(html: &str) -> impl Iterator<Item = &str> {
+    html.split("href=").skip(1).filter_map(|rest| {
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        rest[1..].split(quote).next()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{serve, Request, Response};
+    use super::*;
+
+    const ZIP: &str = "/files/vaccinationsdata-17062021-ab12.zip";
+    const VERSION: &str = "\"v1\"";
+
+    /// The SSI download page and archive, answering with `status` for the archive.
+    fn ssi(status: u16) -> impl Fn(&Request) -> Response {
+        move |request| {
+            let headers = vec![
+                ("ETag", VERSION.to_string()),
+                ("Last-Modified", "Thu, 17 Jun 2021 12:00:00 GMT".to_string()),
+            ];
+            match request.path.as_str() {
+                "/overvagningsdata/download-fil-med-vaccinationsdata" => (
+                    200,
+                    vec![],
+                    format!("<a href=\"/other.pdf\">x</a><a href=\"{}\">y</a>", ZIP),
+                ),
+                ZIP if status == 200 && request.header("If-None-Match") == Some(VERSION) => {
+                    (304, headers, String::new())
+                }
+                ZIP => (status, headers, "archive".to_string()),
+                _ => (404, vec![], String::new()),
+            }
+        }
+    }
+
+    fn target(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("klima-fetch-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn downloads_the_latest_archive() {
+        let (base, _) = serve(ssi(200));
+        let dir = target("200");
+        let download = Fetcher::new(&base, &dir)
+            .unwrap()
+            .fetch(&VACCINATIONS)
+            .unwrap();
+        let written = std::fs::read_to_string(&download.path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(download.updated);
+        assert_eq!(download.url, format!("{}{}", base, ZIP));
+        assert_eq!(written, "archive");
+        assert_eq!(download.sha256, format!("{:x}", Sha256::digest(b"archive")));
+        assert_eq!(download.published(), NaiveDate::from_ymd(2021, 6, 17));
+    }
+
+    #[test]
+    fn unchanged_archive_is_not_downloaded_again() {
+        let (base, seen) = serve(ssi(200));
+        let dir = target("304");
+        let fetcher = Fetcher::new(&base, &dir).unwrap();
+        fetcher.fetch(&VACCINATIONS).unwrap();
+        let again = fetcher.fetch(&VACCINATIONS).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!again.updated);
+        let last = seen.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.path, ZIP);
+        assert_eq!(last.header("If-None-Match"), Some(VERSION));
+    }
+
+    #[test]
+    fn changed_copy_is_downloaded_in_full() {
+        let (base, seen) = serve(ssi(200));
+        let dir = target("hash");
+        let fetcher = Fetcher::new(&base, &dir).unwrap();
+        let first = fetcher.fetch(&VACCINATIONS).unwrap();
+        std::fs::write(&first.path, "truncated").unwrap();
+        let again = fetcher.fetch(&VACCINATIONS).unwrap();
+        let written = std::fs::read_to_string(&again.path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(again.updated);
+        assert_eq!(written, "archive");
+        let last = seen.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.path, ZIP);
+        assert_eq!(last.header("If-None-Match"), None);
+    }
+
+    #[test]
+    fn error_status_fails() {
+        let (base, _) = serve(ssi(500));
+        let dir = target("500");
+        let fetcher = Fetcher::new(&base, &dir).unwrap();
+        let fetched = fetcher.fetch(&VACCINATIONS);
+        let missing = fetcher.fetch(&SURVEILLANCE);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match fetched {
+            Err(FetchError::Status { status, .. }) => assert_eq!(status, 500),
+            other => panic!("expected a status error, got {:?}", other.map(|d| d.url)),
+        }
+        match missing {
+            Err(FetchError::Status { status, .. }) => assert_eq!(status, 404),
+            other => panic!("expected a status error, got {:?}", other.map(|d| d.url)),
+        }
+    }
+}
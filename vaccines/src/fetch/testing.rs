@@ -0,0 +1,73 @@
+//! A local HTTP server standing in for SSI or StatBank in tests.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// A request as the server saw it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    /// The path with the query, e.g. `/data/FOLK1A/CSV?lang=da`.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A response: the status, headers and body.
+pub type Response = (u16, Vec<(&'static str, String)>, String);
+
+/// Serve `respond` on a free local port until the test ends. Returns the base url and the
+/// requests seen so far.
+pub fn serve(
+    respond: impl Fn(&Request) -> Response + Send + 'static,
+) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let seen: Arc<Mutex<Vec<Request>>> = Arc::default();
+    let log = seen.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut request = Request {
+                path: line.split(' ').nth(1).unwrap_or("").to_string(),
+                headers: vec![],
+            };
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                match header.trim().split_once(':') {
+                    Some((name, value)) => request
+                        .headers
+                        .push((name.to_string(), value.trim().to_string())),
+                    None => break,
+                }
+            }
+
+            let (status, headers, body) = respond(&request);
+            log.lock().unwrap().push(request);
+            write!(stream, "HTTP/1.1 {} X\r\n", status).unwrap();
+            for (name, value) in headers {
+                write!(stream, "{}: {}\r\n", name, value).unwrap();
+            }
+            write!(
+                stream,
+                "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    (base, seen)
+}
@@ -0,0 +1,11 @@
+//! Time series of the Danish covid-19 data and the charts drawn from them, for the page made by
+//! the `klima` binary.
+
+#[macro_use]
+extern crate horrorshow;
+
+pub mod fetch;
+pub mod loader;
+pub mod statbank;
+pub mod table;
+pub mod web;
@@ -0,0 +1,107 @@
+use chrono::NaiveDate;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// A zip file as published by SSI, e.g. `vaccinationsdata-<date>.zip`. Files are read from it
+/// directly without unpacking.
+pub struct Archive {
+    zip: zip::ZipArchive<File>,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> zip::result::ZipResult<Self> {
+        Ok(Archive {
+            zip: zip::ZipArchive::new(File::open(path)?)?,
+        })
+    }
+
+    /// The release date in the name of an SSI archive, like 17062021 in
+    /// `vaccinationsdata-dashboard-covid19-17062021-ab12.zip`.
+    pub fn released(path: &Path) -> Option<NaiveDate> {
+        let name = path.file_stem()?.to_str()?;
+        name.split(['-', '_'])
+            .filter(|part| part.len() == 8 && part.chars().all(|c| c.is_ascii_digit()))
+            .find_map(|part| {
+                NaiveDate::parse_from_str(part, "%d%m%Y")
+                    .or_else(|_| NaiveDate::parse_from_str(part, "%Y%m%d"))
+                    .ok()
+            })
+    }
+
+    /// The entry for `file`, e.g. `Vaccine_DB/FoersteVacc_region_dag.csv`, wherever it is in
+    /// the archive. SSI has shipped the same folders both at the top level and below
+    /// `ArcGIS_dashboards_data/`, so entries match on their trailing path components.
+    pub fn find(&self, file: &str) -> Option<String> {
+        let wanted: Vec<&str> = file.split('/').rev().collect();
+        let mut names: Vec<&str> = self
+            .zip
+            .file_names()
+            .filter(|name| {
+                let parts = name.trim_end_matches('/').split(['/', '\\']).rev();
+                wanted.iter().zip(parts).filter(|(w, p)| w == &p).count() == wanted.len()
+            })
+            .collect();
+        // Prefer the shallowest match if a file is in the archive more than once.
+        names.sort_by_key(|name| (name.matches('/').count(), name.to_string()));
+        names.first().map(|name| name.to_string())
+    }
+
+    /// Read `file` from the archive, or `None` if it is not there.
+    pub fn read(&mut self, file: &str) -> zip::result::ZipResult<Option<Vec<u8>>> {
+        let name = match self.find(file) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let mut entry = self.zip.by_name(&name)?;
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn files_are_found_by_their_trailing_path() {
+        let path = std::env::temp_dir().join(format!("klima-find-{}.zip", std::process::id()));
+        zip(
+            &path,
+            &[
+                ("ArcGIS_dashboards_data/Vaccine_DB/a.csv", "nested"),
+                ("Vaccine_DB/a.csv", "top"),
+                ("ArcGIS_dashboards_data/Regionalt_DB/b.csv", "only nested"),
+                ("Andet_DB/a.csv", "other folder"),
+            ],
+        );
+        let mut archive = Archive::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            archive.find("Vaccine_DB/a.csv").unwrap(),
+            "Vaccine_DB/a.csv"
+        );
+        assert_eq!(
+            archive.find("Regionalt_DB/b.csv").unwrap(),
+            "ArcGIS_dashboards_data/Regionalt_DB/b.csv"
+        );
+        assert_eq!(archive.find("Regionalt_DB/a.csv"), None);
+        assert_eq!(archive.find("b.csv/Regionalt_DB"), None);
+        let read = archive.read("Regionalt_DB/b.csv").unwrap().unwrap();
+        assert_eq!(read, b"only nested");
+        assert_eq!(archive.read("c.csv").unwrap(), None);
+    }
+}
@@ -0,0 +1,385 @@
+use crate::table::reader::{self, Duplicate};
+use crate::table::{
+    AgeBandError, Columns, Mode, ParseError, Population, TimeSeriesGroup, Value,
+};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+
+mod archive;
+pub mod owid;
+pub mod schema;
+pub mod snapshot;
+
+pub use archive::Archive;
+pub use schema::{Format, Schema};
+
+/// Directory holding an SSI snapshot, i.e. `Vaccine_DB/` and `Regionalt_DB/`, either unpacked
+/// or as the zip files published by SSI.
+pub struct DataDir {
+    root: PathBuf,
+    archives: Vec<PathBuf>,
+    encoding: Option<&'static Encoding>,
+    mode: Mode,
+    skipped: RefCell<Vec<ParseError>>,
+    warnings: RefCell<Vec<String>>,
+    duplicates: RefCell<Vec<Duplicate>>,
+}
+
+/// Decode a data file. Without an explicit encoding a BOM wins, then UTF-8 if the bytes are
+/// valid UTF-8, and otherwise Windows-1252, which is what SSI uses for most of its CSVs.
+pub fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> String {
+    let encoding = encoding
+        .or_else(|| Encoding::for_bom(bytes).map(|(e, _)| e))
+        .unwrap_or_else(|| {
+            if std::str::from_utf8(bytes).is_ok() {
+                UTF_8
+            } else {
+                WINDOWS_1252
+            }
+        });
+    encoding.decode_with_bom_removal(bytes).0.into_owned()
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Missing { file: String, searched: String },
+    Io { file: String, cause: std::io::Error },
+    Archive { file: String, cause: zip::result::ZipError },
+    Parse(ParseError),
+    AgeBands { file: String, cause: AgeBandError },
+    NoSeries { file: String, tag: String },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Missing { file, searched } => {
+                write!(f, "missing data file {} (looked in {})", file, searched)
+            }
+            LoadError::Io { file, cause } => write!(f, "could not read {}: {}", file, cause),
+            LoadError::Archive { file, cause } => {
+                write!(f, "could not read archive {}: {}", file, cause)
+            }
+            LoadError::Parse(e) => write!(f, "{}", e),
+            LoadError::AgeBands { file, cause } => write!(f, "{}: {}", file, cause),
+            LoadError::NoSeries { file, tag } => write!(f, "{}: no series for {:?}", file, tag),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DataDir {
+            root: root.into(),
+            archives: vec![],
+            encoding: None,
+            mode: Mode::Strict,
+            skipped: RefCell::new(vec![]),
+            warnings: RefCell::new(vec![]),
+            duplicates: RefCell::new(vec![]),
+        }
+    }
+
+    /// Skip malformed rows instead of failing. Skipped rows are available from `skipped`.
+    pub fn lenient(self) -> Self {
+        DataDir {
+            mode: Mode::Lenient,
+            ..self
+        }
+    }
+
+    /// Rows skipped so far in lenient mode.
+    pub fn skipped(&self) -> Vec<ParseError> {
+        self.skipped.borrow().clone()
+    }
+
+    /// Archives that could not be read and were skipped.
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.borrow().clone()
+    }
+
+    fn warn(&self, warning: String) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    /// Dates repeated within a series in the datasets loaded so far.
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        self.duplicates.borrow().clone()
+    }
+
+    fn parsed<T>(
+        &self,
+        file: &str,
+        parsed: Result<(T, Vec<ParseError>), ParseError>,
+    ) -> Result<T, LoadError> {
+        let (t, skipped) = parsed.map_err(|e| LoadError::Parse(e.in_file(file)))?;
+        self.skipped
+            .borrow_mut()
+            .extend(skipped.into_iter().map(|e| e.in_file(file)));
+        Ok(t)
+    }
+
+    /// Decode every file with `encoding` instead of detecting it.
+    pub fn with_encoding(self, encoding: &'static Encoding) -> Self {
+        DataDir {
+            encoding: Some(encoding),
+            ..self
+        }
+    }
+
+    /// Also look for files in `archive`, e.g. `vaccinationsdata-<date>.zip`.
+    pub fn with_archive(mut self, archive: impl Into<PathBuf>) -> Self {
+        self.archives.push(archive.into());
+        self
+    }
+
+    /// Archives added with `with_archive`, followed by the zip files in the data directory,
+    /// newest first by the release date in their name, or when they were written if there is
+    /// none.
+    fn archives(&self) -> Vec<PathBuf> {
+        let mut found: Vec<_> = std::fs::read_dir(&self.root)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|e| e == "zip"))
+                    .filter(|path| !self.archives.contains(path))
+                    .map(|path| {
+                        let modified = path.metadata().and_then(|m| m.modified());
+                        let key = (
+                            Archive::released(&path),
+                            modified.unwrap_or(std::time::UNIX_EPOCH),
+                        );
+                        (key, path)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        found.sort_by(|a, b| b.cmp(a));
+        self.archives
+            .iter()
+            .cloned()
+            .chain(found.into_iter().map(|(_, path)| path))
+            .collect()
+    }
+
+    /// Read a file relative to the data directory, e.g. `Vaccine_DB/FoersteVacc_region_dag.csv`,
+    /// from the newest archive that has it, or else from the directory itself. Archives that
+    /// cannot be read are skipped, see `warnings`.
+    pub fn read(&self, file: &str) -> Result<String, LoadError> {
+        let archives = self.archives();
+        for path in &archives {
+            let read = Archive::open(path).and_then(|mut archive| archive.read(file));
+            match read {
+                Ok(Some(bytes)) => return Ok(decode(&bytes, self.encoding)),
+                Ok(None) => {}
+                Err(cause) => self.warn(
+                    LoadError::Archive {
+                        file: path.display().to_string(),
+                        cause,
+                    }
+                    .to_string(),
+                ),
+            }
+        }
+
+        let path = self.root.join(file);
+        match std::fs::read(&path) {
+            Ok(bytes) => return Ok(decode(&bytes, self.encoding)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(cause) => {
+                return Err(LoadError::Io {
+                    file: path.display().to_string(),
+                    cause,
+                })
+            }
+        }
+
+        let searched = std::iter::once(&self.root)
+            .chain(&archives)
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(LoadError::Missing {
+            file: file.to_string(),
+            searched,
+        })
+    }
+
+    pub fn csv_group<V: Value>(
+        &self,
+        file: &str,
+        tags: im::OrdSet<String>,
+        columns: &Columns,
+    ) -> Result<TimeSeriesGroup<V>, LoadError> {
+        let data = self.read(file)?;
+        self.parsed(
+            file,
+            TimeSeriesGroup::from_csv_mode(tags, &data, columns, self.mode),
+        )
+    }
+
+    /// Load a dataset described in the schema, see `schema::Dataset`.
+    pub fn dataset<V: Value>(
+        &self,
+        dataset: &schema::Dataset,
+    ) -> Result<TimeSeriesGroup<V>, LoadError> {
+        let data = self.read(&dataset.file)?;
+        let mode = if dataset.lenient { Mode::Lenient } else { self.mode };
+        let tags = dataset.tags();
+        let parsed = match dataset.format {
+            Format::Csv => {
+                let columns = dataset.columns();
+                reader::read_rows(&data, &columns, mode).map(|(rows, skipped)| {
+                    let duplicates = reader::duplicates(&rows);
+                    self.duplicates.borrow_mut().extend(duplicates.into_iter().map(|d| Duplicate {
+                        file: Some(dataset.file.clone()),
+                        ..d
+                    }));
+                    (TimeSeriesGroup::from_rows(tags, rows, columns.aggregation), skipped)
+                })
+            }
+            Format::JsonStat => TimeSeriesGroup::from_jsonstat(tags, &data).map(|g| (g, vec![])),
+            Format::SdmxCsv => TimeSeriesGroup::from_sdmx_csv_mode(tags, &data, mode),
+        };
+        let mut group = self.parsed(&dataset.file, parsed)?.with_fill(dataset.fill);
+
+        if dataset.age.is_some() {
+            let age_error = |cause| LoadError::AgeBands {
+                file: dataset.file.clone(),
+                cause,
+            };
+            let bands = dataset.age_bands().map_err(age_error)?;
+            group = group.sort_by_age();
+            if !bands.is_empty() {
+                group = group.merge_age_bands(&bands).map_err(age_error)?;
+            }
+        }
+
+        if dataset.total {
+            group = TimeSeriesGroup::new(vec![group.total(dataset.tags())]);
+        }
+        Ok(group.with_unit(dataset.unit.as_deref()))
+    }
+
+    /// A population table, like StatBank's FOLK1A split by area and age, for
+    /// `TimeSeriesGroup::per_capita`.
+    pub fn population(&self, dataset: &schema::Dataset) -> Result<Population, LoadError> {
+        Ok(Population::new(&self.dataset(dataset)?, &dataset.tags()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::io::Write;
+    use std::path::Path;
+
+    #[test]
+    fn files_are_read_below_the_root() {
+        let root = std::env::temp_dir().join(format!("klima-read-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("Vaccine_DB")).unwrap();
+        std::fs::write(root.join("Vaccine_DB/a.csv"), "Dato;Antal\n").unwrap();
+
+        let data_dir = DataDir::new(&root);
+        let read = data_dir.read("Vaccine_DB/a.csv");
+        let missing = data_dir.read("Vaccine_DB/b.csv");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(read.unwrap(), "Dato;Antal\n");
+        match missing {
+            Err(LoadError::Missing { file, .. }) => assert_eq!(file, "Vaccine_DB/b.csv"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn windows_1252_is_decoded() {
+        // "Første;Sjælland" as SSI writes it.
+        let bytes = b"F\xf8rste;Sj\xe6lland\n";
+        assert_eq!(decode(bytes, None), "Første;Sjælland\n");
+    }
+
+    #[test]
+    fn byte_order_mark_is_removed() {
+        let mut bytes = vec![0xef, 0xbb, 0xbf];
+        bytes.extend_from_slice("Første;Sjælland".as_bytes());
+        assert_eq!(decode(&bytes, None), "Første;Sjælland");
+        assert_eq!(decode("Første".as_bytes(), None), "Første");
+    }
+
+    #[test]
+    fn explicit_encoding_wins() {
+        assert_eq!(decode("Første".as_bytes(), Some(WINDOWS_1252)), "FÃ¸rste");
+    }
+
+    #[test]
+    fn files_missing_from_the_directory_are_read_from_archives() {
+        let root = std::env::temp_dir().join(format!("klima-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("vaccinationsdata.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        zip.start_file(
+            "ArcGIS_dashboards_data/Vaccine_DB/a.csv",
+            Default::default(),
+        )
+        .unwrap();
+        zip.write_all(b"F\xf8rste").unwrap();
+        zip.finish().unwrap();
+
+        let data_dir = DataDir::new(&root);
+        let read = data_dir.read("Vaccine_DB/a.csv");
+        let missing = data_dir.read("Vaccine_DB/b.csv");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(read.unwrap(), "Første");
+        assert!(matches!(missing, Err(LoadError::Missing { .. })));
+    }
+
+    fn zip(path: &Path, file: &str, data: &str) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        zip.start_file(file, zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(data.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn newest_release_wins_and_broken_archives_are_skipped() {
+        let root = std::env::temp_dir().join(format!("klima-archives-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("Vaccine_DB")).unwrap();
+        std::fs::write(root.join("Vaccine_DB/a.csv"), "loose").unwrap();
+        // Written before the older release, so it is not the newest file on disk.
+        zip(&root.join("data-10062021-b.zip"), "Vaccine_DB/a.csv", "new");
+        zip(&root.join("data-01062021-a.zip"), "Vaccine_DB/a.csv", "old");
+        std::fs::write(root.join("data-20062021-c.zip"), "not a zip").unwrap();
+
+        let data_dir = DataDir::new(&root);
+        let read = data_dir.read("Vaccine_DB/a.csv");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(read.unwrap(), "new");
+        assert_eq!(data_dir.warnings().len(), 1);
+    }
+
+    #[test]
+    fn release_date_from_name() {
+        let released = |name: &str| Archive::released(Path::new(name));
+        assert_eq!(
+            released("vaccinationsdata-dashboard-covid19-17062021-ab12.zip"),
+            Some(NaiveDate::from_ymd(2021, 6, 17))
+        );
+        assert_eq!(
+            released("snapshot-20210617.zip"),
+            Some(NaiveDate::from_ymd(2021, 6, 17))
+        );
+        assert_eq!(released("data.zip"), None);
+    }
+}
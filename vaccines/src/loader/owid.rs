@@ -0,0 +1,112 @@
+use super::{DataDir, LoadError};
+use crate::table::{Columns, TimeSeriesGroup};
+
+/// The Our World in Data covid file, from
+/// https://covid.ourworldindata.org/data/owid-covid-data.csv, kept in the data directory.
+pub const OWID_FILE: &str = "owid-covid-data.csv";
+
+const DATE: &str = "date";
+const ISO_CODE: &str = "iso_code";
+const LOCATION: &str = "location";
+
+/// Denmark and the neighbours we are usually compared with.
+pub const NEIGHBOURS: [&str; 4] = ["DNK", "NOR", "SWE", "DEU"];
+
+impl DataDir {
+    /// One series of an OWID metric, e.g. "new_cases" or "people_vaccinated", per country in
+    /// `countries`, given by ISO code like "DNK" and in that order. Each series is tagged with
+    /// `tags`, the ISO code and the country name. Days without a value are left out.
+    pub fn owid(
+        &self,
+        tags: im::OrdSet<String>,
+        metric: &str,
+        countries: &[&str],
+    ) -> Result<TimeSeriesGroup<f64>, LoadError> {
+        // OWID writes counts like "1234.0", has metrics like "new_cases_smoothed_per_million"
+        // with decimals, and leaves unknown values empty.
+        let columns = Columns::new(DATE, metric)
+            .tag(ISO_CODE)
+            .tag(LOCATION)
+            .missing("");
+        let all = self.csv_group(OWID_FILE, tags, &columns)?;
+
+        let series = countries
+            .iter()
+            .map(|&code| {
+                all.series()
+                    .iter()
+                    .find(|ts| ts.tags.contains(code))
+                    .cloned()
+                    .ok_or_else(|| LoadError::NoSeries {
+                        file: OWID_FILE.to_string(),
+                        tag: code.to_string(),
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TimeSeriesGroup::new(series))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// A few rows of the OWID file as downloaded.
+    const RECORDED: &str = include_str!("recorded/owid-covid-data.csv");
+
+    fn data_dir(name: &str) -> (DataDir, std::path::PathBuf) {
+        let root = std::env::temp_dir().join(format!("klima-owid-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(OWID_FILE), RECORDED).unwrap();
+        (DataDir::new(&root), root)
+    }
+
+    #[test]
+    fn neighbours_in_order_per_million() {
+        let (data_dir, root) = data_dir("neighbours");
+        let tags = im::OrdSet::unit("Per million".to_string());
+        let group = data_dir.owid(tags, "new_cases_smoothed_per_million", &NEIGHBOURS);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let group = group.unwrap();
+        let codes: Vec<_> = group
+            .series()
+            .iter()
+            .map(|ts| NEIGHBOURS.iter().find(|c| ts.tags.contains(**c)).cloned())
+            .collect();
+        assert_eq!(
+            codes,
+            vec![Some("DNK"), Some("NOR"), Some("SWE"), Some("DEU")]
+        );
+
+        let denmark = &group.series()[0];
+        let expected: im::OrdSet<String> = vec![
+            "Per million".to_string(),
+            "DNK".to_string(),
+            "Denmark".to_string(),
+        ]
+        .into();
+        assert_eq!(denmark.tags, expected);
+        // The metric keeps its decimals.
+        assert_eq!(denmark.data[&NaiveDate::from_ymd(2021, 6, 1)], 144.372);
+        // Norway has no smoothed value on the second day.
+        assert_eq!(group.series()[1].data.len(), 1);
+    }
+
+    #[test]
+    fn other_countries_are_left_out() {
+        let (data_dir, root) = data_dir("others");
+        let group = data_dir.owid(im::OrdSet::new(), "new_cases", &["SWE"]);
+        let missing = data_dir.owid(im::OrdSet::new(), "new_cases", &["DNK", "ISL"]);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let group = group.unwrap();
+        assert_eq!(group.len(), 1);
+        assert_eq!(group.series()[0].data.values().sum::<f64>(), 2928.0);
+        match missing {
+            Err(LoadError::NoSeries { tag, .. }) => assert_eq!(tag, "ISL"),
+            other => panic!("{:?}", other.map(|g| g.len())),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::table::{AgeBand, AgeBandError, Aggregation, Columns, DateFormat, Fill};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// The datasets we know how to load, read from a TOML file like `datasets.toml`:
+///
+/// ```toml
+/// [[dataset]]
+/// name = "doede"
+/// file = "Regionalt_DB/07_antal_doede_pr_dag_pr_region.csv"
+/// date = "Dato"
+/// values = ["Antal døde"]
+/// tags = ["Antal døde per dag"]
+/// unit = "personer"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct Schema {
+    #[serde(rename = "dataset", default)]
+    pub datasets: Vec<Dataset>,
+}
+
+/// How a data file is laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Delimited text with the columns named in the dataset, like the SSI files.
+    #[default]
+    Csv,
+    /// JSON-stat, e.g. from StatBank or Eurostat. Dimensions become tags.
+    JsonStat,
+    /// SDMX-CSV, e.g. from Eurostat or ECDC. Dimensions become tags.
+    SdmxCsv,
+}
+
+/// How to turn one SSI file into a `TimeSeriesGroup`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Dataset {
+    pub name: String,
+    /// Path in the data directory or archive, e.g. `Vaccine_DB/FoersteVacc_region_dag.csv`.
+    pub file: String,
+    #[serde(default)]
+    pub format: Format,
+    /// Header of the date column. Only used for `csv`, like `date_format`, `values`, `by`,
+    /// `aggregation`, `missing` and `decimals`; the other formats name their dimensions
+    /// themselves.
+    #[serde(default)]
+    pub date: String,
+    /// E.g. "iso", "iso-week", "dotted" or a pattern like "%d/%m/%Y". Detected by default.
+    #[serde(default)]
+    pub date_format: DateFormat,
+    /// Headers of the value columns, added up per row.
+    #[serde(default)]
+    pub values: Vec<String>,
+    /// Columns that split the file into one series per value, e.g. `["Regionsnavn"]`.
+    #[serde(default)]
+    pub by: Vec<String>,
+    /// Column with age bands like "50-59" and "90+", for age-stratified files. Each band
+    /// becomes a series tagged with the band.
+    pub age: Option<String>,
+    /// Merge the age bands into these wider bands, e.g. `["0-59", "60+"]`.
+    #[serde(default)]
+    pub age_bands: Vec<String>,
+    /// How rows with the same date are combined, and the days of a week or month when the
+    /// dataset is resampled: "sum" (the default), "last", "min", "max" or "mean".
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// Values that mean "no data", e.g. `[".."]`. Rows with them are left out.
+    #[serde(default)]
+    pub missing: Vec<String>,
+    /// Values have a decimal point, e.g. "1234.0", and are rounded to whole numbers.
+    #[serde(default)]
+    pub decimals: bool,
+    /// What a date without data means: "zero", "forward", "interpolate" or "gap", the
+    /// default. Used when the series are added up, accumulated or extrapolated.
+    #[serde(default)]
+    pub fill: Fill,
+    /// Add up the series split by `by` into a single series.
+    #[serde(default)]
+    pub total: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub unit: Option<String>,
+    /// Skip malformed rows, e.g. a trailing "I alt" row, even when the run is strict.
+    #[serde(default)]
+    pub lenient: bool,
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io { file: String, cause: std::io::Error },
+    Toml { file: String, cause: toml::de::Error },
+    Unknown(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io { file, cause } => write!(f, "could not read {}: {}", file, cause),
+            SchemaError::Toml { file, cause } => write!(f, "invalid schema {}: {}", file, cause),
+            SchemaError::Unknown(name) => write!(f, "no dataset named {:?} in schema", name),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl Schema {
+    pub fn load(path: &Path) -> Result<Self, SchemaError> {
+        let file = path.display().to_string();
+        let text = std::fs::read_to_string(path).map_err(|cause| SchemaError::Io {
+            file: file.clone(),
+            cause,
+        })?;
+        toml::from_str(&text).map_err(|cause| SchemaError::Toml { file, cause })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Dataset, SchemaError> {
+        self.datasets
+            .iter()
+            .find(|d| d.name == name)
+            .ok_or_else(|| SchemaError::Unknown(name.to_string()))
+    }
+}
+
+impl Dataset {
+    pub fn columns(&self) -> Columns {
+        Columns {
+            date: self.date.clone(),
+            date_format: self.date_format.clone(),
+            values: self.values.clone(),
+            tags: self.by.iter().chain(&self.age).cloned().collect(),
+            aggregation: self.aggregation,
+            missing: self.missing.clone(),
+            decimals: self.decimals,
+        }
+    }
+
+    pub fn age_bands(&self) -> Result<Vec<AgeBand>, AgeBandError> {
+        self.age_bands.iter().map(|b| b.parse()).collect()
+    }
+
+    pub fn tags(&self) -> im::OrdSet<String> {
+        self.tags.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shipped() -> Schema {
+        Schema::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("datasets.toml")).unwrap()
+    }
+
+    #[test]
+    fn shipped_schema_selects_columns_by_name() {
+        let schema = shipped();
+        let columns = schema.get("vacc_foerste").unwrap().columns();
+        assert_eq!(columns.date, "Første vacc. dato");
+        assert_eq!(columns.values, vec!["Antal første vacc."]);
+        assert_eq!(columns.tags, vec!["Regionsnavn", "regionskode_current"]);
+        assert_eq!(columns.aggregation, Aggregation::Sum);
+
+        let smitte = schema.get("smitte").unwrap();
+        assert!(smitte.total);
+        assert_eq!(smitte.unit.as_deref(), Some("personer"));
+        assert_eq!(
+            smitte.tags(),
+            im::OrdSet::unit("Smittede per dag".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_datasets_are_errors() {
+        match shipped().get("nope") {
+            Err(SchemaError::Unknown(name)) => assert_eq!(name, "nope"),
+            other => panic!("{:?}", other),
+        }
+    }
+}
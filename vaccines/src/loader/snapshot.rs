@@ -0,0 +1,180 @@
+use super::{schema, DataDir, LoadError};
+use crate::table::{Mode, Vintages};
+use chrono::NaiveDate;
+use std::cell::RefCell;
+use encoding_rs::Encoding;
+use std::path::{Path, PathBuf};
+
+/// Every SSI release side by side, as `<root>/<publication date>/<archive>.zip`, so the
+/// revisions of earlier days are kept when a new release comes out.
+pub struct SnapshotStore {
+    root: PathBuf,
+    encoding: Option<&'static Encoding>,
+    mode: Mode,
+    warnings: RefCell<Vec<String>>,
+    until: Option<NaiveDate>,
+}
+
+impl DataDir {
+    /// The snapshots in `snapshots/` below the data directory, read with the same settings.
+    pub fn snapshots(&self) -> SnapshotStore {
+        SnapshotStore {
+            root: self.root.join("snapshots"),
+            encoding: self.encoding,
+            mode: self.mode,
+            warnings: RefCell::new(vec![]),
+            until: None,
+        }
+    }
+}
+
+impl SnapshotStore {
+    /// Only use the snapshots published on or before `date`, e.g. the date given with `--as-of`.
+    pub fn until(self, date: NaiveDate) -> Self {
+        SnapshotStore {
+            until: Some(date),
+            ..self
+        }
+    }
+
+    /// Keep a copy of `archive` as published on `published`. Adding it again does nothing.
+    pub fn add(&self, published: NaiveDate, archive: &Path) -> Result<PathBuf, LoadError> {
+        let dir = self.root.join(published.to_string());
+        let target = dir.join(archive.file_name().unwrap_or_default());
+        let io_error = |cause| LoadError::Io {
+            file: target.display().to_string(),
+            cause,
+        };
+        if !target.exists() {
+            std::fs::create_dir_all(&dir).map_err(io_error)?;
+            std::fs::copy(archive, &target).map_err(io_error)?;
+        }
+        Ok(target)
+    }
+
+    /// Publication dates with a snapshot, oldest first, up to the date given with `until`.
+    pub fn publications(&self) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = std::fs::read_dir(&self.root)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                    .filter(|date| self.until.is_none_or(|until| *date <= until))
+                    .collect()
+            })
+            .unwrap_or_default();
+        dates.sort();
+        dates
+    }
+
+    /// The data as known on `published`, i.e. the newest snapshot on or before that date.
+    pub fn as_of(&self, published: NaiveDate) -> Option<DataDir> {
+        let date = self.publications().into_iter().rfind(|d| *d <= published)?;
+        Some(self.data_dir(date))
+    }
+
+    fn data_dir(&self, published: NaiveDate) -> DataDir {
+        DataDir {
+            encoding: self.encoding,
+            mode: self.mode,
+            ..DataDir::new(self.root.join(published.to_string()))
+        }
+    }
+
+    /// A dataset as published in every snapshot, added up to one series like
+    /// `TimeSeriesGroup::total`. Snapshots from before the dataset's file existed are left out,
+    /// and so are snapshots that cannot be read, see `warnings`.
+    pub fn vintages(&self, dataset: &schema::Dataset) -> Vintages {
+        let mut vintages = Vintages::default();
+        for published in self.publications() {
+            let data_dir = self.data_dir(published);
+            match data_dir.dataset(dataset) {
+                Ok(group) => vintages.insert(published, group.total(dataset.tags())),
+                Err(LoadError::Missing { .. }) => {}
+                Err(e) => self
+                    .warnings
+                    .borrow_mut()
+                    .push(format!("skipped the snapshot from {}: {}", published, e)),
+            }
+            self.warnings.borrow_mut().extend(data_dir.warnings());
+        }
+        vintages
+    }
+
+    /// Snapshots and archives that could not be read and were skipped.
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn as_of_picks_the_newest_snapshot_up_to_the_date() {
+        let root = std::env::temp_dir().join(format!("klima-as-of-{}", std::process::id()));
+        for published in &["2021-06-01", "2021-06-05", "2021-06-10"] {
+            std::fs::create_dir_all(root.join("snapshots").join(published)).unwrap();
+        }
+        std::fs::create_dir_all(root.join("snapshots/not-a-date")).unwrap();
+        let snapshots = DataDir::new(&root).snapshots();
+        let publications = snapshots.publications();
+        let as_of = |published| snapshots.as_of(date(published)).map(|d| d.root);
+        let exact = as_of("2021-06-05");
+        let earlier = as_of("2021-06-07");
+        let before_all = as_of("2021-05-31");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            publications,
+            vec![date("2021-06-01"), date("2021-06-05"), date("2021-06-10")]
+        );
+        assert_eq!(exact, Some(root.join("snapshots/2021-06-05")));
+        assert_eq!(earlier, Some(root.join("snapshots/2021-06-05")));
+        assert_eq!(before_all, None);
+    }
+
+    #[test]
+    fn unreadable_vintages_are_skipped() {
+        let root = std::env::temp_dir().join(format!("klima-vintages-{}", std::process::id()));
+        let files = [
+            ("2021-06-01", "Dato;Antal\n2021-05-31;1\n"),
+            ("2021-06-02", "Dato;Antal\n2021-05-31;x\n"),
+            ("2021-06-03", "Dato;Antal\n2021-05-31;3\n"),
+        ];
+        for (published, data) in &files {
+            let dir = root.join("snapshots").join(published);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("a.csv"), data).unwrap();
+        }
+        let dataset: schema::Dataset =
+            toml::from_str("name = 'a'\nfile = 'a.csv'\ndate = 'Dato'\nvalues = ['Antal']")
+                .unwrap();
+        let snapshots = DataDir::new(&root).snapshots();
+        let vintages = snapshots.vintages(&dataset);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let published: Vec<NaiveDate> = vintages.releases.keys().cloned().collect();
+        assert_eq!(published, vec![date("2021-06-01"), date("2021-06-03")]);
+        assert_eq!(snapshots.warnings().len(), 1);
+    }
+
+    #[test]
+    fn until_leaves_out_later_snapshots() {
+        let root = std::env::temp_dir().join(format!("klima-snapshots-{}", std::process::id()));
+        for published in &["2021-06-01", "2021-06-05", "2021-06-10"] {
+            std::fs::create_dir_all(root.join("snapshots").join(published)).unwrap();
+        }
+        let snapshots = DataDir::new(&root).snapshots().until(date("2021-06-07"));
+        let publications = snapshots.publications();
+        let as_of = snapshots.as_of(date("2021-06-20")).map(|d| d.root);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(publications, vec![date("2021-06-01"), date("2021-06-05")]);
+        assert_eq!(as_of, Some(root.join("snapshots/2021-06-05")));
+    }
+}
@@ -4,41 +4,39 @@ extern crate horrorshow;
 use horrorshow::helper::doctype;
 use horrorshow::Template;
 
-use crate::table::{TimeSeries, TimeSeriesGroup};
-use chrono::{Duration, NaiveDate};
+use klima::loader::{DataDir, LoadError, Schema};
+use klima::{fetch, loader, statbank};
+use klima::table::rolling::Window;
+use klima::table::validate::{Report, Rules};
+use klima::table::growth::Cori;
+use klima::table::lag::Lag;
+use klima::table::{Join, Period, TimeSeries, TimeSeriesGroup, Value};
+use chrono::{Duration, NaiveDate, Utc};
 
-mod table;
-mod web;
-
-fn nth_column(n: usize, row: Vec<&str>) -> i64 {
-    row[n].trim().parse().unwrap()
-}
-
-fn last_column(row: Vec<&str>) -> i64 {
-    row.last().unwrap().trim().parse().unwrap()
-}
-
-fn sum_all_columns(row: Vec<&str>) -> i64 {
-    row.iter().map(|c| c.trim().parse::<i64>().unwrap()).sum()
-}
+// The helpers below use the fill policy of the series for dates without data. Dates still
+// without a value, like the days before a goal starts, count as 0.
 
 fn start_from_last(ts: &TimeSeries, date: &NaiveDate) -> i64 {
-    *ts.data.get(date).unwrap_or(&0)
+    ts.value(date).unwrap_or(0)
 }
 
+/// Average of the days with a value in the week up to `date`.
 fn start_from_7d_avg(ts: &TimeSeries, date: &NaiveDate) -> i64 {
-    (0..7)
-        .map(|d| ts.data.get(&(*date - Duration::days(d))).unwrap_or(&0))
-        .sum::<i64>()
-        / 7
+    let values: Vec<i64> = (0..7)
+        .filter_map(|d| ts.value(&(*date - Duration::days(d))))
+        .collect();
+    if values.is_empty() {
+        return 0;
+    }
+    values.iter().sum::<i64>() / values.len() as i64
 }
 
 fn delta_6weeks_avg(ts: &TimeSeries, date: &NaiveDate) -> i64 {
     let days = 6 * 7;
-    let day = |delta| ts.data.get(&(*date - delta)).unwrap_or(&0);
+    let day = |delta| ts.value(&(*date - Duration::days(delta))).unwrap_or(0);
     [
-        day(Duration::days(0)) - day(Duration::days(days)),
-        day(Duration::days(1)) - day(Duration::days(days + 1)),
+        day(0) - day(days),
+        day(1) - day(days + 1),
     ]
     .iter()
     .max()
@@ -46,7 +44,233 @@ fn delta_6weeks_avg(ts: &TimeSeries, date: &NaiveDate) -> i64 {
         / days
 }
 
+/// The 7-day average of the whole group, drawn over the daily numbers.
+fn week_average(group: &TimeSeriesGroup) -> TimeSeries {
+    group
+        .clone()
+        .total(vec!["7-dages gennemsnit".to_string()].into())
+        .rolling(&Window::mean(7))
+}
+
+/// `ts` moved `days` later and as percent of its highest value, to compare its shape with
+/// series of another size.
+fn lagged_percent(ts: &TimeSeries, name: &str, days: i64) -> TimeSeries<f64> {
+    let max = ts.data.values().max().copied().unwrap_or(0).max(1);
+    let label = match days {
+        0 => name.to_string(),
+        _ => format!("{}, {} dage senere", name, days),
+    };
+    TimeSeries {
+        tags: vec![label].into(),
+        ..ts.clone().shift(days).map(Value::to_f64) * (100.0 / max as f64)
+    }
+}
+
+/// "Indlagte følger smittede med 9 dage (korrelation 0,93)."
+fn describe_lag(later: &str, earlier: &str, lag: Option<Lag>) -> String {
+    match lag {
+        Some(lag) => format!(
+            "{} følger {} med {} dage (korrelation {}).",
+            later,
+            earlier,
+            lag.days,
+            format!("{:.2}", lag.correlation).replace('.', ",")
+        ),
+        None => format!("{} kan ikke sammenlignes med {}.", later, earlier),
+    }
+}
+
+/// "Smitten vokser med 3,1 % om dagen og fordobles på 22 dage." from the last week against
+/// the week before.
+fn describe_growth(cases: &TimeSeries) -> String {
+    let last = |ts: TimeSeries<f64>| ts.data.get_max().map(|(_, v)| *v);
+    let decimal = |v: f64, decimals| format!("{:.*}", decimals, v).replace('.', ",");
+    match (last(cases.growth_rate(7)), last(cases.doubling_time(7))) {
+        (Some(rate), Some(days)) if rate > 0.0 => format!(
+            "Smitten vokser med {} % om dagen og fordobles på {} dage.",
+            decimal(rate * 100.0, 1),
+            decimal(days, 0)
+        ),
+        (Some(rate), Some(days)) => format!(
+            "Smitten falder med {} % om dagen og halveres på {} dage.",
+            decimal(-rate * 100.0, 1),
+            decimal(-days, 0)
+        ),
+        _ => "Smitten er uændret.".to_string(),
+    }
+}
+
+/// Load a dataset from the schema and check it, see `Report`.
+fn load(
+    data_dir: &DataDir,
+    schema: &Schema,
+    rules: &Rules,
+    report: &mut Report,
+    name: &str,
+) -> Result<TimeSeriesGroup, failure::Error> {
+    let group = data_dir.dataset(schema.get(name)?)?;
+    report.check(name, &group, rules);
+    Ok(group)
+}
+
+/// Command line options:
+///
+/// * `--data-dir <path>`: where the SSI files are, defaulting to `data`. Files not in the
+///   directory are read from the zip files in it, or from archives given with `--archive <zip>`.
+///   With Our World in Data's `owid-covid-data.csv` there, the page also compares Denmark with
+///   its neighbours, and with a population table like StatBank's `FOLK1A.csv` it shows the
+///   vaccination coverage per region.
+/// * `--statbank`: without a population table in the data directory, get the population of
+///   the regions from StatBank, or from a local server given with `--statbank-url <url>`.
+/// * `--encoding <label>`: decode files with e.g. `windows-1252` instead of detecting it.
+/// * `--lenient`: skip and report malformed rows instead of failing the run.
+/// * `--schema <path>`: the datasets to load, defaulting to `datasets.toml`.
+/// * `--fetch`: download the newest SSI archives into the data directory first, and keep a copy
+///   in its `snapshots/` by publication date. The snapshots are used to correct the last days
+///   of admissions and deaths for reporting delay. The SSI site can be replaced with
+///   `--ssi-url <url>`, e.g. for a local test server.
+/// * `--as-of <date>`: build the page from the snapshot published on or before `date`.
+/// * `--report <path>`: write the problems found in the data, like missing dates or stale
+///   files, as JSON. They are also shown at the top of the page.
+/// * `--revisions <dataset>`: print how each day of a dataset was revised in the four weeks
+///   after it, as a semicolon separated triangle, instead of the page.
+struct Args {
+    root: String,
+    encoding: Option<String>,
+    lenient: bool,
+    archives: Vec<String>,
+    schema: String,
+    fetch: bool,
+    ssi_url: String,
+    statbank: bool,
+    statbank_url: String,
+    as_of: Option<String>,
+    revisions: Option<String>,
+    report: Option<String>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut parsed = Args {
+            root: "data".to_string(),
+            encoding: None,
+            lenient: false,
+            archives: vec![],
+            schema: "datasets.toml".to_string(),
+            fetch: false,
+            ssi_url: fetch::SSI_URL.to_string(),
+            statbank: false,
+            statbank_url: statbank::STATBANK_URL.to_string(),
+            as_of: None,
+            revisions: None,
+            report: None,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let (name, value) = match arg.find('=') {
+                Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || value.clone().or_else(|| args.next());
+            match name.as_str() {
+                "--data-dir" => parsed.root = value().unwrap_or(parsed.root),
+                "--encoding" => parsed.encoding = value(),
+                "--archive" => parsed.archives.extend(value()),
+                "--schema" => parsed.schema = value().unwrap_or(parsed.schema),
+                "--ssi-url" => parsed.ssi_url = value().unwrap_or(parsed.ssi_url),
+                "--lenient" => parsed.lenient = true,
+                "--fetch" => parsed.fetch = true,
+                "--statbank" => parsed.statbank = true,
+                "--statbank-url" => parsed.statbank_url = value().unwrap_or(parsed.statbank_url),
+                "--as-of" => parsed.as_of = value(),
+                "--revisions" => parsed.revisions = value(),
+                "--report" => parsed.report = value(),
+                _ => {}
+            }
+        }
+        parsed
+    }
+
+    fn data_dir(&self) -> Result<DataDir, failure::Error> {
+        let mut data_dir = DataDir::new(&self.root);
+        for archive in &self.archives {
+            data_dir = data_dir.with_archive(archive);
+        }
+        if self.lenient {
+            data_dir = data_dir.lenient();
+        }
+        match &self.encoding {
+            None => Ok(data_dir),
+            Some(label) => match encoding_rs::Encoding::for_label(label.as_bytes()) {
+                Some(e) => Ok(data_dir.with_encoding(e)),
+                None => Err(failure::format_err!("unknown encoding {:?}", label)),
+            },
+        }
+    }
+
+    fn fetch(&self, data_dir: &DataDir) -> Result<(), failure::Error> {
+        let fetcher = fetch::Fetcher::new(&self.ssi_url, &self.root)?;
+        for dataset in &[fetch::VACCINATIONS, fetch::SURVEILLANCE] {
+            let download = fetcher.fetch(dataset)?;
+            data_dir
+                .snapshots()
+                .add(download.published(), &download.path)?;
+            eprintln!(
+                "{} {} (sha256 {})",
+                if download.updated { "downloaded" } else { "unchanged" },
+                download.path.display(),
+                download.sha256
+            );
+        }
+        Ok(())
+    }
+}
+
 fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), failure::Error> {
+    let args = Args::parse();
+    let data_dir = args.data_dir()?;
+    if args.fetch {
+        args.fetch(&data_dir)?;
+    }
+    let schema = Schema::load(args.schema.as_ref())?;
+
+    let as_of = match &args.as_of {
+        None => None,
+        Some(date) => Some(
+            date.parse::<NaiveDate>()
+                .map_err(|_| failure::format_err!("invalid date {:?}", date))?,
+        ),
+    };
+    // Only the snapshots published by then, so an older page is made without later revisions.
+    let snapshots = match as_of {
+        None => data_dir.snapshots(),
+        Some(date) => data_dir.snapshots().until(date),
+    };
+
+    if let Some(name) = &args.revisions {
+        let vintages = snapshots.vintages(schema.get(name)?);
+        // SSI revisions settle within a few weeks.
+        print!("{}", vintages.triangle(28));
+        for warning in snapshots.warnings() {
+            eprintln!("warning: {}", warning);
+        }
+        return Ok(());
+    }
+    let data_dir = match as_of {
+        None => data_dir,
+        Some(date) => snapshots.as_of(date).ok_or_else(|| {
+            failure::format_err!("no snapshot published on or before {}", date)
+        })?,
+    };
+
     let start_date = NaiveDate::from_ymd(2020, 2, 1);
 
     let phase_1 = 1_400_000;
@@ -61,39 +285,26 @@ fn main() {
     // Updated below after extrapolation when setting up `vacciner` timeseries.
     let mut vaccinations_so_far = 0;
 
-    let vaccine_started_data =
-        include_bytes!("../data/Vaccine_DB/FoersteVacc_region_dag.csv");
-    let vaccine_done_data =
-        include_bytes!("../data/Vaccine_DB/FaerdigVacc_region_dag.csv");
-
-    let smitte_data = include_bytes!("../data/Regionalt_DB/08_bekraeftede_tilfaelde_pr_dag_pr_regions.csv");
-    let indlagte_data = include_bytes!("../data/Regionalt_DB/06_nye_indlaeggelser_pr_region_pr_dag.csv");
-    let dode_data = include_bytes!("../data/Regionalt_DB/07_antal_doede_pr_dag_pr_region.csv");
+    // Problems found in the data, shown at the top of the page. The age of the data is
+    // measured from the day the page is made, or the `--as-of` date for an older page.
+    let rules = Rules::new(as_of.unwrap_or_else(|| Utc::today().naive_utc()));
+    let mut report = Report::default();
 
     // People who have started vaccination.
-    let vac_started = TimeSeries::from_str(
-        vec!["Personer med 1 af 2 stik".to_string()].into(),
-        String::from_utf8_lossy(&vaccine_started_data[..]).as_ref(),
-        |r| nth_column(2, r),
-    );
+    let vac_started = load(&data_dir, &schema, &rules, &mut report, "vacc_foerste")?
+        .total(schema.get("vacc_foerste")?.tags());
 
     // People who have started and completed vaccination.
-    let vac_done = TimeSeries::from_str(
-        vec!["Færdigvaccinerede".to_string()].into(),
-        String::from_utf8_lossy(&vaccine_done_data[..]).as_ref(),
-        |r| nth_column(2, r),
-    );
+    let vac_done = load(&data_dir, &schema, &rules, &mut report, "vacc_faerdig")?
+        .total(schema.get("vacc_faerdig")?.tags());
 
     // Do not count someone `done` as `started`. Every person is counted only once.
-    let vac_only_started = TimeSeries::new(
-        vac_started.tags.clone(),
-        vac_started
-            .data
-            .union_with(vac_done.data.clone(), std::ops::Sub::sub),
-    );
+    let vac_only_started = vac_started.sub_with(vac_done.clone(), Join::Outer(0));
+    report.check_series("vacc_kun_foerste", &vac_only_started, &rules);
 
 
     let vacciner = TimeSeriesGroup::new(vec![vac_done, vac_only_started])
+        .with_unit(schema.get("vacc_foerste")?.unit.as_deref())
         .prepend(0, start_date, Duration::days(1))
         .accumulative()
         .out_last_sum(&mut vaccinations_so_far)
@@ -144,134 +355,221 @@ fn main() {
         .unwrap()
     };
 
-    let smitte = TimeSeriesGroup::new(vec![TimeSeries::from_str(
-        vec!["Smittede per dag".to_string()].into(),
-        String::from_utf8_lossy(&smitte_data[..]).as_ref(),
-        last_column,
-    )])
-    .prepend(0, start_date, Duration::days(1))
-    .future_goal(
-        "Mål 1: Minimering af død og alvorlig sygdom",
-        phase_1_end,
-        |now| calc_goal(now, 0.75, phase_1_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 2: Forebyggelse af smittespredning",
-        phase_2_end,
-        |now| calc_goal(now, 0.4, phase_2_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 3: Flok-immunitet",
-        phase_3_end,
-        |now| calc_goal(now, 0.0, phase_3_progress),
-        chrono::Duration::days(1),
-        start_from_last,
-    )
-    .plot_stacked(
-        "smitte",
-        "Antal smittede per dag",
-        "dag",
-        "Antal personer smittet med ny coronavirus per dag",
-    );
+    let smitte = load(&data_dir, &schema, &rules, &mut report, "smitte")?;
+    let smitte_avg = week_average(&smitte);
+    let smitte_total = smitte.clone().total(vec!["Kontakttal".to_string()].into());
+    let smitte_rt = smitte_total.rt(&Cori::default());
+    let kontakttal = TimeSeriesGroup::new(vec![smitte_rt.estimate])
+        .with_overlay(vec![smitte_rt.low, smitte_rt.high])
+        .plot(
+            "kontakttal",
+            "Kontakttal for smitte",
+            "dag",
+            "Kontakttal (Rt) med 95 % troværdighedsinterval",
+        );
+    let smitte = smitte
+        .prepend(0, start_date, Duration::days(1))
+        .future_goal(
+            "Mål 1: Minimering af død og alvorlig sygdom",
+            phase_1_end,
+            |now| calc_goal(now, 0.75, phase_1_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 2: Forebyggelse af smittespredning",
+            phase_2_end,
+            |now| calc_goal(now, 0.4, phase_2_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 3: Flok-immunitet",
+            phase_3_end,
+            |now| calc_goal(now, 0.0, phase_3_progress),
+            chrono::Duration::days(1),
+            start_from_last,
+        )
+        .with_overlay(vec![smitte_avg.clone()])
+        .plot_stacked(
+            "smitte",
+            "Antal smittede per dag",
+            "dag",
+            "Antal personer smittet med ny coronavirus per dag",
+        );
 
-    let indlagte = TimeSeriesGroup::new(vec![TimeSeries::from_str(
-        vec!["Nyindlagte per dag".to_string()].into(),
-        String::from_utf8_lossy(&indlagte_data[..]).as_ref(),
-        last_column,
-    )])
-    .prepend(0, start_date, Duration::days(1))
-    .future_goal(
-        "Mål 1: Minimering af død og alvorlig sygdom",
-        phase_1_end,
-        |now| calc_goal(now, 0.2, phase_1_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 2: Forebyggelse af smittespredning",
-        phase_2_end,
-        |now| calc_goal(now, 0.0, phase_2_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 3: Flok-immunitet",
-        phase_3_end,
-        |now| calc_goal(now, 0.0, phase_3_progress),
-        chrono::Duration::days(1),
-        start_from_last,
-    )
-    .plot_stacked(
-        "indlagte",
-        "Antal indlagte",
-        "dag",
-        "Personer nyindskrevet med ny coronavirus per dag",
-    );
+    // The last days of admissions and deaths are incomplete when published, so they are
+    // corrected by how they were revised in earlier snapshots, if there are any.
+    let published = snapshots.publications().last().cloned();
+    let indlagte_delays = snapshots
+        .vintages(schema.get("indlagte")?)
+        .triangle(28)
+        .delays(14);
+    let mut indlagte_range = vec![];
+    let indlagte = load(&data_dir, &schema, &rules, &mut report, "indlagte")?
+        .nowcast(published, &indlagte_delays, &mut indlagte_range);
+    let indlagte_avg = week_average(&indlagte);
+    let indlagte = indlagte
+        .prepend(0, start_date, Duration::days(1))
+        .future_goal(
+            "Mål 1: Minimering af død og alvorlig sygdom",
+            phase_1_end,
+            |now| calc_goal(now, 0.2, phase_1_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 2: Forebyggelse af smittespredning",
+            phase_2_end,
+            |now| calc_goal(now, 0.0, phase_2_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 3: Flok-immunitet",
+            phase_3_end,
+            |now| calc_goal(now, 0.0, phase_3_progress),
+            chrono::Duration::days(1),
+            start_from_last,
+        )
+        .with_overlay(indlagte_range)
+        .with_overlay(vec![indlagte_avg.clone()])
+        .plot_stacked(
+            "indlagte",
+            "Antal indlagte",
+            "dag",
+            "Personer nyindskrevet med ny coronavirus per dag",
+        );
 
-    let dode = TimeSeriesGroup::new(vec![TimeSeries::from_str(
-        vec!["Antal døde per dag".to_string()].into(),
-        String::from_utf8_lossy(&dode_data[..]).as_ref(),
-        |r| nth_column(0, r),
-    )])
-    .prepend(0, start_date, Duration::days(1))
-    .future_goal(
-        "Mål 1: Minimering af død og alvorlig sygdom",
-        phase_1_end,
-        |now| calc_goal(now, 0.0, phase_1_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 2: Forebyggelse af smittespredning",
-        phase_2_end,
-        |now| calc_goal(now, 0.0, phase_2_progress),
-        chrono::Duration::days(1),
-        start_from_7d_avg,
-    )
-    .future_goal(
-        "Mål 3: Flok-immunitet",
-        phase_3_end,
-        |now| calc_goal(now, 0.0, phase_3_progress),
-        chrono::Duration::days(1),
-        start_from_last,
-    )
-    .plot_stacked(
-        "dode",
-        "Antal døde",
+    let dode_delays = snapshots
+        .vintages(schema.get("doede")?)
+        .triangle(28)
+        .delays(14);
+    let mut dode_range = vec![];
+    let dode = load(&data_dir, &schema, &rules, &mut report, "doede")?
+        .nowcast(published, &dode_delays, &mut dode_range);
+    let dode_avg = week_average(&dode);
+    let dode = dode
+        .prepend(0, start_date, Duration::days(1))
+        .future_goal(
+            "Mål 1: Minimering af død og alvorlig sygdom",
+            phase_1_end,
+            |now| calc_goal(now, 0.0, phase_1_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 2: Forebyggelse af smittespredning",
+            phase_2_end,
+            |now| calc_goal(now, 0.0, phase_2_progress),
+            chrono::Duration::days(1),
+            start_from_7d_avg,
+        )
+        .future_goal(
+            "Mål 3: Flok-immunitet",
+            phase_3_end,
+            |now| calc_goal(now, 0.0, phase_3_progress),
+            chrono::Duration::days(1),
+            start_from_last,
+        )
+        .with_overlay(dode_range)
+        .with_overlay(vec![dode_avg.clone()])
+        .plot_stacked(
+            "dode",
+            "Antal døde",
+            "dag",
+            "Personer der er død med ny coronavirus per dag",
+        );
+
+    // How many days admissions follow cases, and deaths follow admissions, lined up by the
+    // deaths.
+    let indlagte_lag = smitte_avg.best_lag(&indlagte_avg, 28);
+    let dode_lag = indlagte_avg.best_lag(&dode_avg, 28);
+    let days = |lag: Option<Lag>| lag.map_or(0, |lag| lag.days);
+    let forsinkelse = TimeSeriesGroup::new(vec![
+        lagged_percent(&smitte_avg, "Smittede", days(indlagte_lag) + days(dode_lag)),
+        lagged_percent(&indlagte_avg, "Indlagte", days(dode_lag)),
+        lagged_percent(&dode_avg, "Døde", 0),
+    ])
+    .with_decimals(1)
+    .plot(
+        "forsinkelse",
+        "Smittede, indlagte og døde forskudt efter hinanden",
         "dag",
-        "Personer der er død med ny coronavirus per dag",
+        "Procent af det højeste 7-dages gennemsnit",
     );
-    //
-    // let smittede_50 = include_bytes!("../data/smittede_50.csv");
-    // let smittede_60 = include_bytes!("../data/smittede_60.csv");
-    // let smittede_70 = include_bytes!("../data/smittede_70.csv");
-    // let smittede_80 = include_bytes!("../data/smittede_80.csv");
-    // let smittede_90 = include_bytes!("../data/smittede_90.csv");
-    // let smittede_alder = TimeSeriesGroup::new(vec![TimeSeries::from_str(
-    //     vec!["Antal smittede per dag 50-59 år".to_string()].into(),
-    //     String::from_utf8_lossy(&smittede_50[..]).as_ref(),
-    //     last_column,
-    // ), TimeSeries::from_str(
-    //     vec!["Antal smittede per dag 60-69 år".to_string()].into(),
-    //     String::from_utf8_lossy(&smittede_60[..]).as_ref(),
-    //     last_column,
-    // ), TimeSeries::from_str(
-    //     vec!["Antal smittede per dag 70-79 år".to_string()].into(),
-    //     String::from_utf8_lossy(&smittede_70[..]).as_ref(),
-    //     last_column,
-    // ), TimeSeries::from_str(
-    //     vec!["Antal smittede per dag 80-89 år".to_string()].into(),
-    //     String::from_utf8_lossy(&smittede_80[..]).as_ref(),
-    //     last_column,
-    // ), TimeSeries::from_str(
-    //     vec!["Antal smittede per dag 90+ år".to_string()].into(),
-    //     String::from_utf8_lossy(&smittede_90[..]).as_ref(),
-    //     last_column,
-    // )]).diff().plot("smittede_alder", "Smittede per dag efter alder", "dag", "Smittede per dag");
+
+    // Skipped with a warning when the file is missing, as not every SSI release has it.
+    let smittede_alder = match data_dir.dataset::<i64>(schema.get("smitte_alder")?) {
+        Ok(group) => {
+            report.check("smitte_alder", &group, &rules);
+            Some(group.resample(Period::Week).plot(
+                "smittede_alder",
+                "Smittede per uge efter alder",
+                "uge",
+                "Smittede per uge",
+            ))
+        }
+        Err(e @ LoadError::Missing { .. }) => {
+            eprintln!("warning: {}, leaving out cases by age", e);
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only shown when owid-covid-data.csv has been put in the data directory.
+    let nabolande = match data_dir.owid(
+        vec!["Smittede per dag per million".to_string()].into(),
+        "new_cases_smoothed_per_million",
+        &loader::owid::NEIGHBOURS,
+    ) {
+        Ok(group) => {
+            report.check("owid", &group, &rules);
+            Some(group.plot(
+                "nabolande",
+                "Smittede per dag i nabolandene",
+                "dag",
+                "Smittede per dag per million indbyggere (7-dages gennemsnit)",
+            ))
+        }
+        Err(LoadError::Missing { .. }) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only shown when a population table like FOLK1A.csv has been put in the data directory,
+    // or with `--statbank`.
+    let befolkning = schema.get("befolkning")?;
+    let population = match data_dir.population(befolkning) {
+        Ok(population) => Some(population),
+        Err(LoadError::Missing { .. }) if args.statbank => {
+            Some(statbank::StatBank::new(&args.statbank_url)?.population(befolkning.tags())?)
+        }
+        Err(LoadError::Missing { .. }) => None,
+        Err(e) => return Err(e.into()),
+    };
+    let daekning = match population {
+        Some(population) => Some(
+            data_dir
+                .dataset::<i64>(schema.get("vacc_foerste")?)?
+                .accumulative()
+                .per_capita(&population, 100.0)?
+                .with_unit(Some("%"))
+                .with_decimals(1)
+                .plot(
+                    "daekning",
+                    "Andel med første stik per region",
+                    "dag",
+                    "Procent af indbyggerne med første stik",
+                ),
+        ),
+        None => None,
+    };
+
+    report.duplicates(&data_dir.duplicates());
+    if let Some(path) = &args.report {
+        std::fs::write(path, report.to_json())
+            .map_err(|e| failure::format_err!("cannot write {}: {}", path, e))?;
+    }
 
     let html = html! {
           : doctype::HTML;
@@ -285,6 +583,22 @@ fn main() {
              }
              body {
                 div(class="container") {
+                  @ if !report.is_empty() {
+                    div(class="row mt-3") {
+                      div(class="col col-lg-12 alert alert-warning") {
+                        details {
+                          summary {
+                            strong : format!("{} af tallene ser forkerte ud, så graferne kan være misvisende.", report.findings.len())
+                          }
+                          ul(class="mb-0 mt-2") {
+                            @ for finding in &report.findings {
+                              li : finding.to_string()
+                            }
+                          }
+                        }
+                      }
+                    }
+                  }
                   div(class="row") {
                     div(class="col col-lg-12") {
                       blockquote(class="blockquote lead") {
@@ -369,9 +683,11 @@ fn main() {
                     div(class="col col-lg-12") {
                       : smitte
                     }
-                    // div(class="col col-lg-12") {
-                    //   : smittede_alder
-                    // }
+                    @ if let Some(smittede_alder) = smittede_alder {
+                      div(class="col col-lg-12") {
+                        : smittede_alder
+                      }
+                    }
                     blockquote(class="blockquote lead") {
                       p(class="mb-0") {
                         : "Jeg forventer først at se et markant dyk i antal smittede, når vi har vaccineret 60-80% af danskerne. Husk på, at samfundsaktivitet og vores opførsel også i høj grad driver smitten. Så vejen bliver ikke en lige linje i virkeligheden."
@@ -379,6 +695,47 @@ fn main() {
                     }
                   }
                   hr {}
+                  div(class="row") {
+                    div(class="col col-lg-12") {
+                      : kontakttal
+                    }
+                    blockquote(class="blockquote lead") {
+                      p(class="mb-0") {
+                        : describe_growth(&smitte_total)
+                      }
+                    }
+                  }
+                  hr {}
+                  div(class="row") {
+                    div(class="col col-lg-12") {
+                      : forsinkelse
+                    }
+                    blockquote(class="blockquote lead") {
+                      p(class="mb-0") {
+                        : describe_lag("Indlagte", "smittede", indlagte_lag)
+                      }
+                      p(class="mb-0") {
+                        : describe_lag("Døde", "indlagte", dode_lag)
+                      }
+                    }
+                  }
+                  @ if let Some(daekning) = daekning {
+                    hr {}
+                    div(class="row") {
+                      div(class="col col-lg-12") {
+                        : daekning
+                      }
+                    }
+                  }
+                  @ if let Some(nabolande) = nabolande {
+                    hr {}
+                    div(class="row") {
+                      div(class="col col-lg-12") {
+                        : nabolande
+                      }
+                    }
+                  }
+                  hr {}
                   div(class="row") {
                     a(href="https://github.com/brinchj/ssi/tree/master/vaccines", target="_blank") {
                       : "Kildekode på Github"
@@ -389,5 +746,13 @@ fn main() {
             }
     };
 
-    println!("{}", html.into_string().unwrap());
+    for e in data_dir.skipped() {
+        eprintln!("warning: skipped {}", e);
+    }
+    for warning in data_dir.warnings().into_iter().chain(snapshots.warnings()) {
+        eprintln!("warning: {}", warning);
+    }
+
+    println!("{}", html.into_string()?);
+    Ok(())
 }
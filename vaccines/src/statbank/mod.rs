@@ -0,0 +1,347 @@
+use crate::table::{Columns, ParseError, Population, TimeSeriesGroup};
+use reqwest::blocking::Client;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use std::fmt;
+
+/// The StatBank API of Statistics Denmark.
+pub const STATBANK_URL: &str = "https://api.statbank.dk/v1";
+
+/// The time and value columns of StatBank CSV files. The other columns are the variables.
+const TIME: &str = "TID";
+const CONTENT: &str = "INDHOLD";
+
+/// The whole country and its regions in FOLK1A.
+const REGIONS: &[&str] = &["000", "081", "082", "083", "084", "085"];
+
+/// A StatBank table as described by `tableinfo`, e.g. FOLK1A.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TableInfo {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub unit: String,
+    pub updated: String,
+    pub variables: Vec<Variable>,
+}
+
+/// A variable of a table, like region, sex, age or time, and the values it can take.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Variable {
+    pub id: String,
+    pub text: String,
+    /// The variable can be left out of a query, adding up over its values.
+    #[serde(default)]
+    pub elimination: bool,
+    #[serde(default)]
+    pub time: bool,
+    pub values: Vec<VariableValue>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct VariableValue {
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    JsonStat,
+}
+
+/// The data to fetch from a table. Every variable in the query splits the result into a series
+/// per value; variables left out are added up by StatBank where the table allows it.
+#[derive(Clone, Debug)]
+pub struct Query {
+    pub table: String,
+    pub variables: Vec<(String, Vec<String>)>,
+    pub format: Format,
+    /// Tag series with value codes like "101" instead of texts like "København".
+    pub codes: bool,
+}
+
+impl Query {
+    pub fn new(table: &str) -> Self {
+        Query {
+            table: table.to_string(),
+            variables: vec![],
+            format: Format::Csv,
+            codes: false,
+        }
+    }
+
+    /// Select values of a variable by their ids, e.g. `select("OMRÅDE", &["000", "101"])`.
+    /// `"*"` selects all values. Time must always be selected, e.g. `select("Tid", &["*"])`.
+    pub fn select(mut self, variable: &str, values: &[&str]) -> Self {
+        self.variables.push((
+            variable.to_string(),
+            values.iter().map(|v| v.to_string()).collect(),
+        ));
+        self
+    }
+
+    pub fn format(self, format: Format) -> Self {
+        Query { format, ..self }
+    }
+
+    pub fn codes(self) -> Self {
+        Query {
+            codes: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StatBankError {
+    BadUrl(String),
+    Http(reqwest::Error),
+    Status {
+        url: String,
+        status: StatusCode,
+    },
+    Json {
+        url: String,
+        cause: serde_json::Error,
+    },
+    Parse {
+        url: String,
+        cause: ParseError,
+    },
+}
+
+impl fmt::Display for StatBankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StatBankError::BadUrl(url) => write!(f, "invalid url {}", url),
+            StatBankError::Http(e) => write!(f, "{}", e),
+            StatBankError::Status { url, status } => write!(f, "{} returned {}", url, status),
+            StatBankError::Json { url, cause } => {
+                write!(f, "invalid response from {}: {}", url, cause)
+            }
+            StatBankError::Parse { url, cause } => write!(f, "{} ({})", cause, url),
+        }
+    }
+}
+
+impl std::error::Error for StatBankError {}
+
+impl From<reqwest::Error> for StatBankError {
+    fn from(e: reqwest::Error) -> Self {
+        StatBankError::Http(e)
+    }
+}
+
+/// Fetches tables from StatBank, or from a local server with recorded responses laid out like
+/// the API, e.g. `tableinfo/FOLK1A` and `data/FOLK1A/CSV`.
+pub struct StatBank {
+    base: Url,
+    client: Client,
+    lang: String,
+}
+
+impl StatBank {
+    /// `base` is normally `STATBANK_URL`.
+    pub fn new(base: &str) -> Result<Self, StatBankError> {
+        // Paths are joined onto the base, so make sure it is treated as a directory.
+        let dir = format!("{}/", base.trim_end_matches('/'));
+        Ok(StatBank {
+            base: Url::parse(&dir).map_err(|_| StatBankError::BadUrl(base.to_string()))?,
+            client: Client::new(),
+            lang: "da".to_string(),
+        })
+    }
+
+    /// Texts in "da" (the default) or "en".
+    pub fn lang(self, lang: &str) -> Self {
+        StatBank {
+            lang: lang.to_string(),
+            ..self
+        }
+    }
+
+    fn get(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<(String, String), StatBankError> {
+        let mut url = self.base.join(path).unwrap();
+        url.query_pairs_mut()
+            .append_pair("lang", &self.lang)
+            .extend_pairs(query);
+        let response = self.client.get(url.clone()).send()?;
+        if !response.status().is_success() {
+            return Err(StatBankError::Status {
+                url: url.to_string(),
+                status: response.status(),
+            });
+        }
+        Ok((url.to_string(), response.text()?))
+    }
+
+    pub fn table_info(&self, table: &str) -> Result<TableInfo, StatBankError> {
+        let format = [("format".to_string(), "JSON".to_string())];
+        let (url, text) = self.get(&format!("tableinfo/{}", table), &format)?;
+        serde_json::from_str(&text).map_err(|cause| StatBankError::Json { url, cause })
+    }
+
+    /// One series per combination of the selected variable values, tagged with `tags` and those
+    /// values. Values StatBank does not have, written as "..", are left out.
+    pub fn data(
+        &self,
+        tags: im::OrdSet<String>,
+        query: &Query,
+    ) -> Result<TimeSeriesGroup, StatBankError> {
+        let format = match query.format {
+            Format::Csv => "CSV",
+            Format::JsonStat => "JSONSTAT",
+        };
+        let presentation = if query.codes { "Code" } else { "Value" };
+        let params: Vec<(String, String)> =
+            std::iter::once(("valuePresentation".to_string(), presentation.to_string()))
+                .chain(
+                    query
+                        .variables
+                        .iter()
+                        .map(|(variable, values)| (variable.clone(), values.join(","))),
+                )
+                .collect();
+        let (url, text) = self.get(&format!("data/{}/{}", query.table, format), &params)?;
+        let text = text.trim_start_matches('\u{feff}');
+
+        let parsed = match query.format {
+            Format::Csv => from_csv(tags, text),
+            Format::JsonStat => TimeSeriesGroup::from_jsonstat(tags, text),
+        };
+        parsed.map_err(|cause| StatBankError::Parse { url, cause })
+    }
+
+    /// The population of Denmark and its regions in the newest quarter of FOLK1A, for
+    /// `TimeSeriesGroup::per_capita` without a population table in the data directory.
+    pub fn population(&self, tags: im::OrdSet<String>) -> Result<Population, StatBankError> {
+        let info = self.table_info("FOLK1A")?;
+        let time = info.variables.iter().find(|v| v.time);
+        let latest = time
+            .and_then(|v| v.values.last())
+            .map_or("*", |value| value.id.as_str());
+        let query = Query::new(&info.id)
+            .select("OMRÅDE", REGIONS)
+            .select(time.map_or("Tid", |v| v.id.as_str()), &[latest]);
+        let group = self.data(tags.clone(), &query)?;
+        Ok(Population::new(&group, &tags))
+    }
+}
+
+fn from_csv(tags: im::OrdSet<String>, data: &str) -> Result<TimeSeriesGroup, ParseError> {
+    let header = data.lines().next().unwrap_or("");
+    let columns = header
+        .split(';')
+        .map(str::trim)
+        .filter(|c| !c.is_empty() && *c != TIME && *c != CONTENT)
+        .fold(Columns::new(TIME, CONTENT), |columns, c| columns.tag(c))
+        .missing("..");
+    TimeSeriesGroup::from_csv(tags, data, &columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::testing::{serve, Request};
+    use std::sync::{Arc, Mutex};
+
+    /// StatBank as recorded, or 404 for anything else.
+    fn recorded() -> (String, Arc<Mutex<Vec<Request>>>) {
+        serve(|request| {
+            let path = request.path.split('?').next().unwrap();
+            match path {
+                "/tableinfo/FOLK1A" => (
+                    200,
+                    vec![],
+                    include_str!("recorded/tableinfo-FOLK1A.json").to_string(),
+                ),
+                "/data/FOLK1A/CSV" => (
+                    200,
+                    vec![],
+                    format!("\u{feff}{}", include_str!("recorded/data-FOLK1A.csv")),
+                ),
+                _ => (404, vec![], String::new()),
+            }
+        })
+    }
+
+    #[test]
+    fn table_info() {
+        let (base, _) = recorded();
+        let info = StatBank::new(&base).unwrap().table_info("FOLK1A").unwrap();
+        assert_eq!(info.id, "FOLK1A");
+        assert_eq!(info.variables.len(), 4);
+        let time: Vec<&Variable> = info.variables.iter().filter(|v| v.time).collect();
+        assert_eq!(time.len(), 1);
+        assert_eq!(time[0].values.last().unwrap().id, "2021K2");
+    }
+
+    #[test]
+    fn one_series_per_region() {
+        let (base, seen) = recorded();
+        let query = Query::new("FOLK1A")
+            .select("OMRÅDE", &["000", "081", "082", "083", "084", "085"])
+            .select("Tid", &["2021K2"]);
+        let tags: im::OrdSet<String> = vec!["Befolkning".to_string()].into();
+        let group = StatBank::new(&base).unwrap().data(tags, &query).unwrap();
+
+        assert_eq!(group.len(), 6);
+        let region = |name: &str| {
+            group
+                .series()
+                .iter()
+                .find(|ts| ts.tags.contains(name))
+                .map(|ts| ts.data.values().sum::<i64>())
+        };
+        assert_eq!(region("Region Hovedstaden"), Some(1_855_084));
+        assert_eq!(region("Hele landet"), Some(5_840_045));
+
+        let query = seen.lock().unwrap().last().unwrap().path.clone();
+        assert!(query.starts_with("/data/FOLK1A/CSV?"));
+        assert!(query.contains("Tid=2021K2"));
+        assert!(query.contains("=000%2C081%2C082%2C083%2C084%2C085"));
+    }
+
+    #[test]
+    fn population_of_the_regions_in_the_newest_quarter() {
+        let (base, seen) = recorded();
+        let tags: im::OrdSet<String> = vec!["Befolkning".to_string()].into();
+        let population = StatBank::new(&base).unwrap().population(tags).unwrap();
+
+        let region = |name: &str| vec![name.to_string(), "Smittede".to_string()].into();
+        assert_eq!(population.get(&region("Hovedstaden")), Some(1_855_084));
+        assert_eq!(population.get(&region("Nordjylland")), Some(590_439));
+        assert_eq!(population.get(&im::OrdSet::new()), Some(5_840_045));
+
+        let query = seen.lock().unwrap().last().unwrap().path.clone();
+        assert!(query.contains("Tid=2021K2"));
+    }
+
+    #[test]
+    fn missing_values_are_left_out() {
+        let data = "OMRÅDE;TID;INDHOLD\nHele landet;2021K1;..\nHele landet;2021K2;5840045\n";
+        let group = from_csv(im::OrdSet::new(), data).unwrap();
+        assert_eq!(group.series()[0].data.len(), 1);
+        assert!(from_csv(
+            im::OrdSet::new(),
+            "OMRÅDE;TID;INDHOLD\nHele landet;2021K2;x\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn error_status_fails() {
+        let (base, _) = recorded();
+        match StatBank::new(&base).unwrap().table_info("FOLK2") {
+            Err(StatBankError::Status { status, .. }) => assert_eq!(status, 404),
+            other => panic!("expected a status error, got {:?}", other.map(|i| i.id)),
+        }
+    }
+}
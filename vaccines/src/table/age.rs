@@ -0,0 +1,248 @@
+use super::{TimeSeries, TimeSeriesGroup, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// An age band like "50-59" or "90+", both ends inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AgeBand {
+    pub from: u32,
+    /// `None` for open-ended bands like "90+".
+    pub to: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AgeBandError {
+    Invalid(String),
+    /// No target band covers the whole of this band, e.g. "55-64" when merging into "0-59".
+    Unmatched(String),
+}
+
+impl fmt::Display for AgeBandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AgeBandError::Invalid(band) => write!(f, "invalid age band {:?}", band),
+            AgeBandError::Unmatched(band) => {
+                write!(f, "age band {:?} does not fit in any merged band", band)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AgeBandError {}
+
+impl FromStr for AgeBand {
+    type Err = AgeBandError;
+
+    /// Accepts "50-59", "90+", "90-" and SSI variants like "50 - 59 år". A single age is only
+    /// accepted with "år", like "50 år" in StatBank, as plain numbers are usually codes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AgeBandError::Invalid(s.to_string());
+        let band = s.trim().trim_end_matches("år").trim();
+        let number = |n: &str| n.trim().parse::<u32>().map_err(|_| invalid());
+
+        if band.len() < s.trim().len() {
+            if let Ok(age) = band.parse() {
+                return Ok(AgeBand {
+                    from: age,
+                    to: Some(age),
+                });
+            }
+        }
+
+        if let Some(from) = band.strip_suffix('+') {
+            return Ok(AgeBand {
+                from: number(from)?,
+                to: None,
+            });
+        }
+        let mut parts = band.splitn(2, '-');
+        let from = number(parts.next().unwrap_or(""))?;
+        let to = match parts.next().map(str::trim) {
+            None => return Err(invalid()),
+            Some("") => None,
+            Some(to) => Some(number(to)?),
+        };
+        if to.is_some_and(|to| to < from) {
+            return Err(invalid());
+        }
+        Ok(AgeBand { from, to })
+    }
+}
+
+impl fmt::Display for AgeBand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to {
+            Some(to) => write!(f, "{}-{}", self.from, to),
+            None => write!(f, "{}+", self.from),
+        }
+    }
+}
+
+impl AgeBand {
+    pub fn contains(&self, other: &AgeBand) -> bool {
+        let to_fits = match (self.to, other.to) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(to), Some(other_to)) => other_to <= to,
+        };
+        self.from <= other.from && to_fits
+    }
+}
+
+/// The age band among the tags of a series, if any.
+fn age_tag<V>(ts: &TimeSeries<V>) -> Option<(String, AgeBand)> {
+    ts.tags
+        .iter()
+        .find_map(|t| t.parse().ok().map(|band| (t.clone(), band)))
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// Order series by their age band tag, youngest first. Series without one go last.
+    pub fn sort_by_age(mut self) -> Self {
+        self.series
+            .sort_by_key(|ts| age_tag(ts).map_or((1, None), |(_, band)| (0, Some(band))));
+        self
+    }
+
+    /// Add up series into wider age bands, e.g. "0-59" and "60+". Each series must have an
+    /// age band tag that fits inside one of `bands`; series without an age band are kept.
+    pub fn merge_age_bands(self, bands: &[AgeBand]) -> Result<Self, AgeBandError> {
+        let mut merged: Vec<(AgeBand, TimeSeries<V>)> = vec![];
+        let mut others = vec![];
+
+        for ts in self.series {
+            let (tag, band) = match age_tag(&ts) {
+                Some(found) => found,
+                None => {
+                    others.push(ts);
+                    continue;
+                }
+            };
+            let target = *bands
+                .iter()
+                .find(|b| b.contains(&band))
+                .ok_or_else(|| AgeBandError::Unmatched(tag.clone()))?;
+            let ts = TimeSeries {
+                tags: ts.tags.without(&tag).update(target.to_string()),
+                ..ts
+            };
+
+            match merged.iter_mut().find(|(b, m)| *b == target && m.tags == ts.tags) {
+                Some((_, m)) => *m = m.clone() + ts,
+                None => merged.push((target, ts)),
+            }
+        }
+
+        merged.sort_by_key(|(band, _)| *band);
+        Ok(TimeSeriesGroup {
+            series: merged.into_iter().map(|(_, ts)| ts).chain(others).collect(),
+            ..self
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn band(s: &str) -> AgeBand {
+        s.parse().unwrap()
+    }
+
+    fn series(tag: &str, values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit(tag.to_string()), data)
+    }
+
+    #[test]
+    fn bands_are_parsed() {
+        assert_eq!(
+            band("50-59"),
+            AgeBand {
+                from: 50,
+                to: Some(59)
+            }
+        );
+        assert_eq!(
+            band(" 50 - 59 år"),
+            AgeBand {
+                from: 50,
+                to: Some(59)
+            }
+        );
+        assert_eq!(band("90+"), AgeBand { from: 90, to: None });
+        assert_eq!(band("90-"), AgeBand { from: 90, to: None });
+        assert_eq!(band("90+").to_string(), "90+");
+        for text in &["", "I alt", "59-50", "50", "a-b"] {
+            assert_eq!(
+                text.parse::<AgeBand>(),
+                Err(AgeBandError::Invalid(text.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn wider_bands_contain_narrower_ones() {
+        assert!(band("0-59").contains(&band("50-59")));
+        assert!(band("60+").contains(&band("90+")));
+        assert!(band("60+").contains(&band("60-69")));
+        assert!(!band("0-59").contains(&band("55-64")));
+        assert!(!band("0-59").contains(&band("90+")));
+        assert!(!band("60-69").contains(&band("50-59")));
+    }
+
+    #[test]
+    fn bands_of_different_lengths_are_merged() {
+        let group = TimeSeriesGroup::new(vec![
+            series("90+", &[("2021-01-01", 1)]),
+            series(
+                "50-54",
+                &[("2021-01-01", 1), ("2021-01-02", 2), ("2021-01-03", 3)],
+            ),
+            series("55-59", &[("2021-01-01", 10)]),
+            series("I alt", &[("2021-01-01", 100)]),
+        ]);
+        let merged = group.merge_age_bands(&[band("0-59"), band("60+")]).unwrap();
+
+        let tags: Vec<_> = merged.series().iter().map(|ts| ts.tags.clone()).collect();
+        assert_eq!(
+            tags,
+            vec![
+                im::OrdSet::unit("0-59".to_string()),
+                im::OrdSet::unit("60+".to_string()),
+                im::OrdSet::unit("I alt".to_string()),
+            ]
+        );
+        let young = &merged.series()[0].data;
+        assert_eq!(young.len(), 3);
+        assert_eq!(young[&date("2021-01-01")], 11);
+        assert_eq!(young[&date("2021-01-03")], 3);
+    }
+
+    #[test]
+    fn bands_that_fit_nowhere_are_errors() {
+        let group = TimeSeriesGroup::new(vec![series("55-64", &[("2021-01-01", 1)])]);
+        assert_eq!(
+            group.merge_age_bands(&[band("0-59"), band("60+")]).err(),
+            Some(AgeBandError::Unmatched("55-64".to_string()))
+        );
+    }
+
+    #[test]
+    fn youngest_first() {
+        let group = TimeSeriesGroup::new(vec![
+            series("I alt", &[]),
+            series("60+", &[]),
+            series("0-59", &[]),
+        ])
+        .sort_by_age();
+        let tags: Vec<_> = group.series().iter().map(|ts| ts.tags.clone()).collect();
+        assert_eq!(tags[0], im::OrdSet::unit("0-59".to_string()));
+        assert_eq!(tags[2], im::OrdSet::unit("I alt".to_string()));
+    }
+}
@@ -0,0 +1,290 @@
+use chrono::{NaiveDate, Weekday};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// How dates are written in a data file. Periods longer than a day, like ISO weeks and
+/// months, are keyed by their first day.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DateFormat {
+    /// Any of the formats below, except `Pattern`, as long as the date is unambiguous.
+    #[default]
+    Auto,
+    /// "2021-02-05"
+    Iso,
+    /// "2021-W05", or "2021-W05-5" with a weekday.
+    IsoWeek,
+    /// "05-02-2021"
+    DayMonthYear,
+    /// "05.02.2021"
+    Dotted,
+    /// "2021M02D05", as exported by Statistics Denmark.
+    StatBank,
+    /// "2021M02"
+    Month,
+    /// "2021-02", as in SDMX files.
+    IsoMonth,
+    /// "2021K1" in StatBank tables, or "2021-Q1" and "2021Q1" in SDMX files.
+    Quarter,
+    /// "2021U05", an ISO week in StatBank tables.
+    StatBankWeek,
+    /// "2021"
+    Year,
+    /// A chrono format string, e.g. "%d/%m/%Y".
+    Pattern(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateError {
+    /// The text is not written in the expected format.
+    Format,
+    /// The text has the right format, but no such date exists, e.g. "2021-02-30".
+    Invalid,
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(DateFormat::Auto),
+            "iso" => Ok(DateFormat::Iso),
+            "iso-week" => Ok(DateFormat::IsoWeek),
+            "day-month-year" => Ok(DateFormat::DayMonthYear),
+            "dotted" => Ok(DateFormat::Dotted),
+            "statbank" => Ok(DateFormat::StatBank),
+            "month" => Ok(DateFormat::Month),
+            "iso-month" => Ok(DateFormat::IsoMonth),
+            "quarter" => Ok(DateFormat::Quarter),
+            "statbank-week" => Ok(DateFormat::StatBankWeek),
+            "year" => Ok(DateFormat::Year),
+            _ if s.contains('%') => Ok(DateFormat::Pattern(s.to_string())),
+            _ => Err(format!("unknown date format {:?}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DateError::Format => write!(f, "unrecognised date"),
+            DateError::Invalid => write!(f, "no such date"),
+        }
+    }
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A number made of ASCII digits only, so "+1" or " 1" are not accepted.
+fn number<T: FromStr>(s: &str) -> Result<T, DateError> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DateError::Format);
+    }
+    s.parse().map_err(|_| DateError::Format)
+}
+
+/// Split "a<sep>b<sep>c" into exactly three parts.
+fn three(s: &str, sep: char) -> Result<(&str, &str, &str), DateError> {
+    let mut parts = s.split(sep);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(a), Some(b), Some(c), None) => Ok((a, b, c)),
+        _ => Err(DateError::Format),
+    }
+}
+
+fn ymd(year: &str, month: &str, day: &str) -> Result<NaiveDate, DateError> {
+    if year.len() != 4 || month.len() > 2 || day.len() > 2 {
+        return Err(DateError::Format);
+    }
+    NaiveDate::from_ymd_opt(number(year)?, number(month)?, number(day)?).ok_or(DateError::Invalid)
+}
+
+impl DateFormat {
+    pub fn parse(&self, s: &str) -> Result<NaiveDate, DateError> {
+        let s = s.trim().trim_matches('"');
+        match self {
+            DateFormat::Auto => Self::detect(s).ok_or(DateError::Format)?.parse(s),
+            DateFormat::Iso => {
+                let (y, m, d) = three(s, '-')?;
+                ymd(y, m, d)
+            }
+            DateFormat::DayMonthYear => {
+                let (d, m, y) = three(s, '-')?;
+                ymd(y, m, d)
+            }
+            DateFormat::Dotted => {
+                let (d, m, y) = three(s, '.')?;
+                ymd(y, m, d)
+            }
+            DateFormat::IsoWeek => {
+                let (year, rest) = s.split_at(s.find("-W").ok_or(DateError::Format)?);
+                let mut rest = rest[2..].splitn(2, '-');
+                let week = rest.next().unwrap_or("");
+                let weekday = match rest.next().map(number::<usize>).transpose()? {
+                    None => Weekday::Mon,
+                    Some(d @ 1..=7) => WEEKDAYS[d - 1],
+                    Some(_) => return Err(DateError::Invalid),
+                };
+                if year.len() != 4 || week.len() != 2 {
+                    return Err(DateError::Format);
+                }
+                NaiveDate::from_isoywd_opt(number(year)?, number(week)?, weekday)
+                    .ok_or(DateError::Invalid)
+            }
+            DateFormat::StatBank => {
+                let (year, rest) = s.split_at(s.find('M').ok_or(DateError::Format)?);
+                let (month, day) = rest[1..].split_at(rest.find('D').ok_or(DateError::Format)? - 1);
+                ymd(year, month, &day[1..])
+            }
+            DateFormat::Month => {
+                let (year, month) = s.split_at(s.find('M').ok_or(DateError::Format)?);
+                ymd(year, &month[1..], "1")
+            }
+            DateFormat::IsoMonth => {
+                let mut parts = s.splitn(2, '-');
+                let year = parts.next().unwrap_or("");
+                let month = parts.next().ok_or(DateError::Format)?;
+                if month.len() != 2 {
+                    return Err(DateError::Format);
+                }
+                ymd(year, month, "1")
+            }
+            DateFormat::Quarter => {
+                let (year, quarter) = s.split_at(s.find(['K', 'Q']).ok_or(DateError::Format)?);
+                let year = year.strip_suffix('-').unwrap_or(year);
+                if quarter.len() != 2 {
+                    return Err(DateError::Format);
+                }
+                match number::<u32>(&quarter[1..])? {
+                    q @ 1..=4 => ymd(year, &(3 * q - 2).to_string(), "1"),
+                    _ => Err(DateError::Invalid),
+                }
+            }
+            DateFormat::StatBankWeek => {
+                let (year, week) = s.split_at(s.find('U').ok_or(DateError::Format)?);
+                DateFormat::IsoWeek.parse(&format!("{}-W{}", year, &week[1..]))
+            }
+            DateFormat::Year => ymd(s, "1", "1"),
+            DateFormat::Pattern(pattern) => {
+                NaiveDate::parse_from_str(s, pattern).map_err(|_| DateError::Format)
+            }
+        }
+    }
+
+    /// The format `Auto` uses for `s`, judged from its separators and the length of its parts.
+    fn detect(s: &str) -> Option<DateFormat> {
+        let year_first = s.len() >= 4 && s.bytes().take(4).all(|b| b.is_ascii_digit());
+        if s.contains("-W") {
+            Some(DateFormat::IsoWeek)
+        } else if s.contains('U') {
+            Some(DateFormat::StatBankWeek)
+        } else if s.contains(['K', 'Q']) {
+            Some(DateFormat::Quarter)
+        } else if s.contains('M') && s.contains('D') {
+            Some(DateFormat::StatBank)
+        } else if s.contains('M') {
+            Some(DateFormat::Month)
+        } else if s.contains('.') {
+            Some(DateFormat::Dotted)
+        } else if s.matches('-').count() == 1 && year_first {
+            Some(DateFormat::IsoMonth)
+        } else if s.contains('-') && year_first {
+            Some(DateFormat::Iso)
+        } else if s.contains('-') {
+            Some(DateFormat::DayMonthYear)
+        } else if s.len() == 4 && year_first {
+            Some(DateFormat::Year)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn detects_every_format() {
+        let cases = [
+            ("2021-02-05", "2021-02-05"),
+            ("2021-W05", "2021-02-01"),
+            ("2021-W05-5", "2021-02-05"),
+            ("05-02-2021", "2021-02-05"),
+            ("05.02.2021", "2021-02-05"),
+            ("2021M02D05", "2021-02-05"),
+            ("2021M02", "2021-02-01"),
+            ("2021-02", "2021-02-01"),
+            ("2021K2", "2021-04-01"),
+            ("2021-Q2", "2021-04-01"),
+            ("2021Q2", "2021-04-01"),
+            ("2021U05", "2021-02-01"),
+            ("2021", "2021-01-01"),
+            (" \"2021-02-05\" ", "2021-02-05"),
+        ];
+        for (text, expected) in &cases {
+            assert_eq!(DateFormat::Auto.parse(text), Ok(date(expected)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn impossible_dates_are_invalid() {
+        for text in &[
+            "2021-02-30",
+            "30.02.2021",
+            "2021-W54",
+            "2021M13",
+            "2021K5",
+            "2021-13",
+        ] {
+            assert_eq!(
+                DateFormat::Auto.parse(text),
+                Err(DateError::Invalid),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn other_text_is_not_a_date() {
+        for text in &["", "Dato", "+2021-02-05", "21-02-05", "2021/02/05", "I alt"] {
+            assert_eq!(
+                DateFormat::Auto.parse(text),
+                Err(DateError::Format),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn patterns_are_not_detected() {
+        let pattern: DateFormat = "%d/%m/%Y".parse().unwrap();
+        assert_eq!(pattern.parse("05/02/2021"), Ok(date("2021-02-05")));
+        assert_eq!("iso-week".parse(), Ok(DateFormat::IsoWeek));
+        assert!("weekly".parse::<DateFormat>().is_err());
+    }
+}
@@ -0,0 +1,367 @@
+use super::{Fill, TimeSeries, TimeSeriesGroup, Value};
+use chrono::{Duration, NaiveDate};
+use std::ops::Add;
+
+/// A series kept as one value every `step` days from `start`, for long series and many of
+/// them, like one per municipality and age band. Operations are a single pass over the values,
+/// where `TimeSeries` rebuilds its map. Dates without data are `None` and are filled like in
+/// `TimeSeries`. See `benches/series.rs` for how the two compare.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DenseSeries<V = i64> {
+    pub tags: im::OrdSet<String>,
+    pub start: NaiveDate,
+    /// Days between two values, e.g. 7 for weekly data.
+    pub step: i64,
+    pub values: Vec<Option<V>>,
+    pub fill: Fill,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl<V: Value> DenseSeries<V> {
+    /// `None` for a series without data. The step is the largest one that has room for every
+    /// date, so nothing is lost.
+    pub fn from_series(ts: &TimeSeries<V>) -> Option<Self> {
+        let start = *ts.data.keys().next()?;
+        let end = *ts.latest_date()?;
+        let step = ts
+            .data
+            .keys()
+            .map(|date| (*date - start).num_days())
+            .fold(0, gcd)
+            .max(1);
+        let mut values = vec![None; ((end - start).num_days() / step) as usize + 1];
+        for (date, value) in &ts.data {
+            values[((*date - start).num_days() / step) as usize] = Some(*value);
+        }
+        Some(DenseSeries {
+            tags: ts.tags.clone(),
+            start,
+            step,
+            values,
+            fill: ts.fill,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn date(&self, index: usize) -> NaiveDate {
+        self.start + Duration::days(index as i64 * self.step)
+    }
+
+    pub fn end(&self) -> NaiveDate {
+        self.date(self.len().saturating_sub(1))
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        (0..self.len()).map(move |i| self.date(i))
+    }
+
+    /// Where `date` is in `values`, if it is one of the dates of the series.
+    pub fn index(&self, date: NaiveDate) -> Option<usize> {
+        let days = (date - self.start).num_days();
+        if days < 0 || days % self.step != 0 {
+            return None;
+        }
+        Some((days / self.step) as usize).filter(|i| *i < self.len())
+    }
+
+    /// The value for `date`. Only zero is filled here, as forward and interpolated values need
+    /// the dates around it; call `filled` first to fill every date in one go.
+    pub fn value(&self, date: NaiveDate) -> Option<V> {
+        if date > self.end() {
+            return None;
+        }
+        let value = self.index(date).and_then(|i| self.values[i]);
+        match self.fill {
+            Fill::Zero => value.or_else(|| Some(V::default())),
+            _ => value,
+        }
+    }
+
+    pub fn with_fill(self, fill: Fill) -> Self {
+        DenseSeries { fill, ..self }
+    }
+
+    /// The same values with a smaller step that divides the current one, and room for the
+    /// dates in between.
+    fn with_step(self, step: i64) -> Self {
+        let factor = (self.step / step) as usize;
+        if factor == 1 || self.is_empty() {
+            return DenseSeries { step, ..self };
+        }
+        let mut values = vec![None; (self.len() - 1) * factor + 1];
+        for (i, value) in self.values.into_iter().enumerate() {
+            values[i * factor] = value;
+        }
+        DenseSeries {
+            step,
+            values,
+            ..self
+        }
+    }
+
+    /// Fill the missing values by the fill policy, like `TimeSeries::filled`.
+    pub fn filled(mut self) -> Self {
+        match self.fill {
+            Fill::Zero => {
+                for value in &mut self.values {
+                    value.get_or_insert(V::default());
+                }
+            }
+            Fill::Forward => {
+                let mut last = None;
+                for value in &mut self.values {
+                    match value {
+                        Some(v) => last = Some(*v),
+                        None => *value = last,
+                    }
+                }
+            }
+            Fill::Interpolate => {
+                let mut last: Option<(usize, V)> = None;
+                for i in 0..self.values.len() {
+                    let to = match self.values[i] {
+                        Some(to) => to,
+                        None => continue,
+                    };
+                    if let Some((before, from)) = last {
+                        let span = (i - before) as f64;
+                        for j in before + 1..i {
+                            let part = (j - before) as f64 / span;
+                            self.values[j] = Some(from + V::from_f64((to - from).to_f64() * part));
+                        }
+                    }
+                    last = Some((i, to));
+                }
+            }
+            Fill::Gap => {}
+        }
+        self
+    }
+
+    /// Running total, like `TimeSeries::accumulative`. With `Fill::Zero` the total is carried
+    /// on to `final_date`, which may take a smaller step when it is between two dates.
+    pub fn accumulative(self, final_date: NaiveDate) -> Self {
+        let mut dense = self.filled();
+        if dense.fill == Fill::Zero && !dense.is_empty() && final_date > dense.end() {
+            let days = (final_date - dense.start).num_days();
+            let step = gcd(dense.step, days);
+            dense = dense.with_step(step);
+            let len = (days / dense.step) as usize + 1;
+            dense.values.resize(len, None);
+            dense.values[len - 1] = Some(V::default());
+        }
+        let mut total = V::default();
+        for value in dense.values.iter_mut().flatten() {
+            total = total + *value;
+            *value = total;
+        }
+        dense
+    }
+
+    /// The change since the value one step before, like `TimeSeries::diff`, but for every
+    /// date with a value once filled.
+    pub fn diff(self) -> Self {
+        let dense = self.filled();
+        let values = dense
+            .values
+            .windows(2)
+            .map(|pair| Some(pair[1]? - pair[0]?))
+            .collect();
+        DenseSeries {
+            start: dense.start + Duration::days(dense.step),
+            values,
+            ..dense
+        }
+    }
+
+    /// Add `val` for the dates back to `start`, like `TimeSeries::prepend`.
+    pub fn prepend(self, val: V, start: NaiveDate) -> Self {
+        let days = (self.start - start).num_days();
+        if days <= 0 {
+            return self;
+        }
+        let n = ((days + self.step - 1) / self.step) as usize;
+        let mut values = vec![Some(val); n];
+        values.extend(self.values);
+        DenseSeries {
+            start: self.start - Duration::days(n as i64 * self.step),
+            values,
+            ..self
+        }
+    }
+}
+
+impl<V: Value> From<DenseSeries<V>> for TimeSeries<V> {
+    fn from(dense: DenseSeries<V>) -> Self {
+        let data = dense
+            .dates()
+            .zip(dense.values.iter())
+            .filter_map(|(date, value)| Some((date, (*value)?)))
+            .collect();
+        TimeSeries::new(dense.tags, data).with_fill(dense.fill)
+    }
+}
+
+/// The sum on every date of either series, filled like `Add` for `TimeSeries`: a side without
+/// a value counts as zero.
+impl<V: Value> Add for DenseSeries<V> {
+    type Output = DenseSeries<V>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let step = gcd(
+            gcd(self.step, rhs.step),
+            (rhs.start - self.start).num_days(),
+        );
+        let (lhs, rhs) = (self.with_step(step).filled(), rhs.with_step(step).filled());
+        let start = lhs.start.min(rhs.start);
+        let len = (lhs.end().max(rhs.end()) - start).num_days() / step + 1;
+        let values = (0..len)
+            .map(|i| {
+                let date = start + Duration::days(i * step);
+                match (lhs.value(date), rhs.value(date)) {
+                    (None, None) => None,
+                    (left, right) => Some(left.unwrap_or_default() + right.unwrap_or_default()),
+                }
+            })
+            .collect();
+        let fill = if lhs.fill == rhs.fill {
+            lhs.fill
+        } else {
+            Fill::Gap
+        };
+        DenseSeries {
+            tags: lhs.tags.union(rhs.tags),
+            start,
+            step,
+            values,
+            fill,
+        }
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// The series in dense form. Series without data are left out.
+    pub fn dense(&self) -> Vec<DenseSeries<V>> {
+        self.series
+            .iter()
+            .filter_map(DenseSeries::from_series)
+            .collect()
+    }
+
+    pub fn from_dense(series: Vec<DenseSeries<V>>) -> Self {
+        Self::new(series.into_iter().map(TimeSeries::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn weekly() -> TimeSeries {
+        let data = vec![(date("2021-01-04"), 3), (date("2021-01-11"), 4)]
+            .into_iter()
+            .collect();
+        TimeSeries::new(im::OrdSet::new(), data).with_fill(Fill::Zero)
+    }
+
+    #[test]
+    fn accumulative_stops_at_the_final_date() {
+        for final_date in &[date("2021-01-15"), date("2021-01-18"), date("2021-01-25")] {
+            let dense = DenseSeries::from_series(&weekly()).unwrap();
+            let expected = weekly().accumulative(*final_date).data;
+            assert_eq!(
+                TimeSeries::from(dense.accumulative(*final_date)).data,
+                expected
+            );
+        }
+    }
+
+    fn series(fill: Fill, values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit("a".to_string()), data).with_fill(fill)
+    }
+
+    fn gappy(fill: Fill) -> TimeSeries {
+        series(
+            fill,
+            &[("2021-01-01", 1), ("2021-01-02", 4), ("2021-01-05", 10)],
+        )
+    }
+
+    fn dense(ts: &TimeSeries) -> DenseSeries {
+        DenseSeries::from_series(ts).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let ts = gappy(Fill::Gap);
+        let daily = dense(&ts);
+        assert_eq!(
+            (daily.start, daily.step, daily.len()),
+            (date("2021-01-01"), 1, 5)
+        );
+        assert_eq!(TimeSeries::from(daily).data, ts.data);
+
+        let weekly = dense(&weekly());
+        assert_eq!((weekly.step, weekly.len()), (7, 2));
+        assert!(DenseSeries::from_series(&TimeSeries::<i64>::default()).is_none());
+    }
+
+    #[test]
+    fn filled_like_time_series() {
+        for fill in &[Fill::Zero, Fill::Forward, Fill::Interpolate, Fill::Gap] {
+            let ts = gappy(*fill);
+            assert_eq!(TimeSeries::from(dense(&ts).filled()).data, ts.filled().data);
+        }
+    }
+
+    #[test]
+    fn diff_like_time_series() {
+        for fill in &[Fill::Zero, Fill::Forward, Fill::Interpolate] {
+            let ts = gappy(*fill);
+            let expected = ts.clone().filled().diff().data;
+            assert_eq!(TimeSeries::from(dense(&ts).diff()).data, expected);
+        }
+    }
+
+    #[test]
+    fn prepend_like_time_series() {
+        let ts = weekly();
+        let expected = ts.clone().prepend(0, date("2020-12-20"), Duration::days(7));
+        let prepended = dense(&ts).prepend(0, date("2020-12-20"));
+        assert_eq!(prepended.start, date("2020-12-14"));
+        assert_eq!(TimeSeries::from(prepended).data, expected.data);
+    }
+
+    #[test]
+    fn add_like_time_series() {
+        // The dense sum has every date, so the filled dates are compared too.
+        for fill in &[Fill::Zero, Fill::Gap] {
+            let (ts, short) = (gappy(*fill), series(*fill, &[("2021-01-02", 100)]));
+            let expected = (ts.clone() + short.clone()).filled();
+            let sum = dense(&ts) + dense(&short);
+            assert_eq!(TimeSeries::from(sum).data, expected.data);
+        }
+        let expected = (weekly() + gappy(Fill::Zero)).filled();
+        let sum = dense(&weekly()) + dense(&gappy(Fill::Zero));
+        assert_eq!(TimeSeries::from(sum).data, expected.data);
+    }
+}
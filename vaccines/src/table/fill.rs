@@ -0,0 +1,141 @@
+use super::{TimeSeries, TimeSeriesGroup, Value};
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+
+/// What a date without data means for a series, used wherever a value is needed for it:
+/// sums, `accumulative`, `diff`, charts and the start and speed of goals. Dates after the last
+/// one are never filled, as nothing is known about them yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fill {
+    /// Nothing happened on that date. The SSI files leave out days without any cases, and
+    /// regions without any before their first case.
+    Zero,
+    /// The value is the same as on the date before, e.g. for a total that is not updated daily.
+    Forward,
+    /// On a straight line between the dates before and after.
+    Interpolate,
+    /// The value is unknown. Charts show a gap and sums with the date are unknown too.
+    #[default]
+    Gap,
+}
+
+impl<V: Value> TimeSeries<V> {
+    pub fn with_fill(self, fill: Fill) -> Self {
+        TimeSeries { fill, ..self }
+    }
+
+    /// The value for `date`, or what the fill policy makes of it when the date has no data.
+    pub fn value(&self, date: &NaiveDate) -> Option<V> {
+        if let Some(value) = self.data.get(date) {
+            return Some(*value);
+        }
+        if Some(date) > self.latest_date() {
+            return None;
+        }
+        match self.fill {
+            Fill::Zero => Some(V::default()),
+            Fill::Forward => self.data.get_prev(date).map(|(_, v)| *v),
+            Fill::Interpolate => {
+                let (before, from) = self.data.get_prev(date)?;
+                let (after, to) = self.data.get_next(date)?;
+                let part =
+                    (*date - *before).num_days() as f64 / (*after - *before).num_days() as f64;
+                Some(*from + V::from_f64((*to - *from).to_f64() * part))
+            }
+            Fill::Gap => None,
+        }
+    }
+
+    /// The most common number of days between two dates of the series, e.g. 7 for weekly data.
+    pub fn step(&self) -> Option<i64> {
+        let mut gaps: im::OrdMap<i64, usize> = im::OrdMap::new();
+        let dates: Vec<&NaiveDate> = self.data.keys().collect();
+        for pair in dates.windows(2) {
+            *gaps.entry((*pair[1] - *pair[0]).num_days()).or_default() += 1;
+        }
+        gaps.into_iter()
+            .max_by_key(|(gap, count)| (*count, -gap))
+            .map(|(gap, _)| gap)
+    }
+
+    /// Put the filled values in the data, at the usual step of the series.
+    pub fn filled(self) -> Self {
+        let (mut date, last) = match (self.data.keys().next(), self.latest_date()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return self,
+        };
+        let step = Duration::days(self.step().unwrap_or(1));
+        let mut data = self.data.clone();
+        while date <= last {
+            if let Some(value) = self.value(&date) {
+                data.entry(date).or_insert(value);
+            }
+            date += step;
+        }
+        TimeSeries { data, ..self }
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    pub fn with_fill(self, fill: Fill) -> Self {
+        TimeSeriesGroup {
+            series: self
+                .series
+                .into_iter()
+                .map(|ts| ts.with_fill(fill))
+                .collect(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn series(fill: Fill) -> TimeSeries {
+        let data = vec![(date("2021-01-01"), 10), (date("2021-01-05"), 30)]
+            .into_iter()
+            .collect();
+        TimeSeries::new(im::OrdSet::new(), data).with_fill(fill)
+    }
+
+    #[test]
+    fn value_by_the_fill_policy() {
+        let between = date("2021-01-02");
+        assert_eq!(series(Fill::Zero).value(&between), Some(0));
+        assert_eq!(series(Fill::Forward).value(&between), Some(10));
+        assert_eq!(series(Fill::Interpolate).value(&between), Some(15));
+        assert_eq!(series(Fill::Gap).value(&between), None);
+    }
+
+    #[test]
+    fn nothing_is_filled_around_the_data() {
+        for fill in &[Fill::Zero, Fill::Forward, Fill::Interpolate, Fill::Gap] {
+            assert_eq!(series(*fill).value(&date("2021-01-06")), None);
+        }
+        assert_eq!(series(Fill::Forward).value(&date("2020-12-31")), None);
+        assert_eq!(series(Fill::Interpolate).value(&date("2020-12-31")), None);
+    }
+
+    #[test]
+    fn filled_at_the_step_of_the_series() {
+        let data = vec![
+            (date("2021-01-04"), 1),
+            (date("2021-01-11"), 2),
+            (date("2021-01-25"), 4),
+        ]
+        .into_iter()
+        .collect();
+        let weekly = TimeSeries::new(im::OrdSet::new(), data).with_fill(Fill::Interpolate);
+        assert_eq!(weekly.step(), Some(7));
+        let filled = weekly.filled();
+        assert_eq!(filled.data.len(), 4);
+        assert_eq!(filled.data.get(&date("2021-01-18")), Some(&3));
+    }
+}
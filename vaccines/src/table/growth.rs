@@ -0,0 +1,266 @@
+//! Growth of daily counts like `smitte`: the daily growth rate, the doubling time and the
+//! reproduction number Rt, estimated after Cori et al. (2013).
+
+use super::rolling::Window;
+use super::{TimeSeries, Value};
+use chrono::Duration;
+
+/// The 2.5th and 97.5th percentiles of a normal distribution, for 95% credible intervals.
+const Z_95: f64 = 1.959964;
+
+/// Cases in a window needed for an estimate of Rt. Cori et al. find that fewer give too wide an
+/// interval to be of use.
+const MIN_CASES: f64 = 12.0;
+
+/// How many days after someone falls ill the people they infect fall ill, as the chance of each
+/// number of days from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerialInterval {
+    pub weights: Vec<f64>,
+}
+
+impl SerialInterval {
+    /// A gamma distribution with `mean` and standard deviation `sd` in days, cut off after
+    /// `max_days`.
+    pub fn gamma(mean: f64, sd: f64, max_days: usize) -> Self {
+        let shape = (mean / sd).powi(2);
+        let scale = sd * sd / mean;
+        let density = |days: f64| days.powf(shape - 1.0) * (-days / scale).exp();
+        let weights: Vec<f64> = (1..=max_days).map(|days| density(days as f64)).collect();
+        let total: f64 = weights.iter().sum();
+        SerialInterval {
+            weights: weights.into_iter().map(|w| w / total).collect(),
+        }
+    }
+}
+
+/// 4.7 days on average with a standard deviation of 2.9, as estimated for SARS-CoV-2 by
+/// Nishiura et al. (2020).
+impl Default for SerialInterval {
+    fn default() -> Self {
+        SerialInterval::gamma(4.7, 2.9, 20)
+    }
+}
+
+/// How `TimeSeries::rt` estimates Rt.
+#[derive(Clone, Debug)]
+pub struct Cori {
+    pub serial_interval: SerialInterval,
+    /// Days of cases behind each estimate. Longer windows give a smoother Rt that is slower to
+    /// follow changes.
+    pub window: usize,
+    /// The gamma prior for Rt, which matters little once there are cases.
+    pub prior_mean: f64,
+    pub prior_sd: f64,
+}
+
+impl Default for Cori {
+    fn default() -> Self {
+        Cori {
+            serial_interval: SerialInterval::default(),
+            window: 7,
+            prior_mean: 5.0,
+            prior_sd: 5.0,
+        }
+    }
+}
+
+impl Cori {
+    pub fn serial_interval(self, serial_interval: SerialInterval) -> Self {
+        Cori {
+            serial_interval,
+            ..self
+        }
+    }
+
+    pub fn window(self, window: usize) -> Self {
+        Cori { window, ..self }
+    }
+}
+
+/// The reproduction number, with the 95% credible interval.
+pub struct Rt {
+    pub estimate: TimeSeries<f64>,
+    pub low: TimeSeries<f64>,
+    pub high: TimeSeries<f64>,
+}
+
+/// The Wilson–Hilferty approximation of a quantile of a gamma distribution, which is close for
+/// the shapes here as they grow with the number of cases.
+fn gamma_quantile(shape: f64, scale: f64, z: f64) -> f64 {
+    let cube = 1.0 - 1.0 / (9.0 * shape) + z / (3.0 * shape.sqrt());
+    (shape * scale * cube.powi(3)).max(0.0)
+}
+
+impl<V: Value> TimeSeries<V> {
+    /// How much the series grows per day, e.g. 0.05 for 5% a day, from the sum of the last
+    /// `days` days against the `days` before them. Dates where either sum is 0 have none.
+    pub fn growth_rate(&self, days: usize) -> TimeSeries<f64> {
+        let sums = self.rolling(&Window::sum(days));
+        let before = Duration::days(days as i64);
+        let data = sums
+            .data
+            .iter()
+            .filter_map(|(date, sum)| {
+                let (sum, earlier) = (sum.to_f64(), sums.data.get(&(*date - before))?.to_f64());
+                if sum <= 0.0 || earlier <= 0.0 {
+                    return None;
+                }
+                Some((*date, (sum / earlier).powf(1.0 / days as f64) - 1.0))
+            })
+            .collect();
+        TimeSeries::new(self.tags.clone(), data)
+    }
+
+    /// Days until the series doubles at its growth rate, see `growth_rate`. A falling series
+    /// has a negative number: the days until it is halved.
+    pub fn doubling_time(&self, days: usize) -> TimeSeries<f64> {
+        let growth = self.growth_rate(days);
+        let data = growth
+            .data
+            .iter()
+            .filter(|(_, rate)| **rate != 0.0)
+            .map(|(date, rate)| (*date, 2f64.ln() / rate.ln_1p()))
+            .collect();
+        TimeSeries { data, ..growth }
+    }
+
+    /// The reproduction number of a series of new cases per day: the cases in a window against
+    /// those expected from the cases before them, by the serial interval. There is no estimate
+    /// until the serial interval is covered, or with fewer than 12 cases in the window.
+    pub fn rt(&self, cori: &Cori) -> Rt {
+        let mut estimate = im::OrdMap::new();
+        let mut low = im::OrdMap::new();
+        let mut high = im::OrdMap::new();
+        if let (Some(first), Some(last)) = (self.data.keys().next(), self.latest_date()) {
+            let dates: Vec<_> = (0..=(*last - *first).num_days())
+                .map(|days| *first + Duration::days(days))
+                .collect();
+            let cases: Vec<f64> = dates
+                .iter()
+                .map(|date| self.value(date).map_or(0.0, Value::to_f64))
+                .collect();
+            // How many cases are expected on each day from the days before it.
+            let infectious: Vec<f64> = (0..cases.len())
+                .map(|i| {
+                    let weights = cori.serial_interval.weights.iter().take(i);
+                    weights.enumerate().map(|(k, w)| w * cases[i - k - 1]).sum()
+                })
+                .collect();
+            let shape = (cori.prior_mean / cori.prior_sd).powi(2);
+            let scale = cori.prior_sd.powi(2) / cori.prior_mean;
+            let start = cori.serial_interval.weights.len() + cori.window.max(1) - 1;
+            for (end, date) in dates.iter().enumerate().skip(start) {
+                let window = end + 1 - cori.window.max(1)..=end;
+                let count: f64 = cases[window.clone()].iter().sum();
+                let expected: f64 = infectious[window].iter().sum();
+                if count < MIN_CASES || expected <= 0.0 {
+                    continue;
+                }
+                let (shape, scale) = (shape + count, 1.0 / (1.0 / scale + expected));
+                estimate.insert(*date, shape * scale);
+                low.insert(*date, gamma_quantile(shape, scale, -Z_95));
+                high.insert(*date, gamma_quantile(shape, scale, Z_95));
+            }
+        }
+        let range = |name: &str, data| TimeSeries::new(self.tags.update(name.to_string()), data);
+        Rt {
+            estimate: TimeSeries::new(self.tags.clone(), estimate),
+            low: range("Nedre grænse", low),
+            high: range("Øvre grænse", high),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn start() -> NaiveDate {
+        NaiveDate::from_ymd(2021, 1, 1)
+    }
+
+    /// `first` cases on the first day, growing by `rate` a day.
+    fn exponential(first: f64, rate: f64, days: i64) -> TimeSeries<f64> {
+        let data = (0..days)
+            .map(|day| {
+                (
+                    start() + Duration::days(day),
+                    first * (1.0 + rate).powi(day as i32),
+                )
+            })
+            .collect();
+        TimeSeries::new(im::OrdSet::new(), data)
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-3 * b.abs()
+    }
+
+    #[test]
+    fn growth_rate_and_doubling_time() {
+        let cases = exponential(100.0, 0.05, 30);
+        let rate = cases.growth_rate(7);
+        assert_eq!(
+            rate.data.keys().next(),
+            Some(&(start() + Duration::days(13)))
+        );
+        assert!(rate.data.values().all(|r| close(*r, 0.05)));
+        let doubling = 2f64.ln() / 1.05f64.ln();
+        assert!(cases
+            .doubling_time(7)
+            .data
+            .values()
+            .all(|d| close(*d, doubling)));
+
+        let falling = exponential(1000.0, -0.05, 30).doubling_time(7);
+        assert!(falling
+            .data
+            .values()
+            .all(|d| close(*d, 2f64.ln() / 0.95f64.ln())));
+    }
+
+    #[test]
+    fn rt_of_exponential_growth() {
+        // With cases growing by `rate` a day, each case infects 1 / Σ w(k) (1 + rate)^-k.
+        let rate: f64 = 0.1;
+        let serial_interval = SerialInterval {
+            weights: vec![0.25, 0.5, 0.25],
+        };
+        let expected = 1.0
+            / serial_interval
+                .weights
+                .iter()
+                .enumerate()
+                .map(|(k, w)| w * (1.0 + rate).powi(-(k as i32 + 1)))
+                .sum::<f64>();
+        let cori = Cori::default().serial_interval(serial_interval).window(5);
+        let rt = exponential(1000.0, rate, 40).rt(&cori);
+
+        // Not before the serial interval and the window are covered.
+        assert_eq!(
+            rt.estimate.data.keys().next(),
+            Some(&(start() + Duration::days(7)))
+        );
+        assert_eq!(rt.estimate.data.len(), 33);
+        for (date, estimate) in &rt.estimate.data {
+            assert!(
+                close(*estimate, expected),
+                "{} {} {}",
+                date,
+                estimate,
+                expected
+            );
+            assert!(rt.low.data[date] < *estimate && *estimate < rt.high.data[date]);
+        }
+    }
+
+    #[test]
+    fn no_rt_with_few_cases() {
+        let rt = exponential(1.0, 0.0, 40).rt(&Cori::default());
+        assert!(rt.estimate.data.is_empty());
+        let rt = exponential(2.0, 0.0, 40).rt(&Cori::default());
+        assert!(rt.estimate.data.values().all(|r| (r - 1.0).abs() < 0.1));
+    }
+}
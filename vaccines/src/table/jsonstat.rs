@@ -0,0 +1,267 @@
+use super::date::DateFormat;
+use super::reader::{parse_date, Aggregation, ParseError, ParseErrorKind};
+use super::{aggregate, TimeSeries, TimeSeriesGroup, Values};
+use chrono::NaiveDate;
+use serde_json::Value;
+
+/// A dimension of a JSON-stat dataset with the ids and labels of its categories in index order.
+struct Dimension {
+    id: String,
+    ids: Vec<String>,
+    labels: Vec<String>,
+}
+
+fn malformed(what: &str, value: impl ToString) -> ParseError {
+    ParseError::new(ParseErrorKind::Malformed, what, &value.to_string())
+}
+
+fn dimension(id: &str, json: &Value) -> Result<Dimension, ParseError> {
+    let category = &json["category"];
+    let ids: Vec<String> = match &category["index"] {
+        Value::Array(ids) => ids
+            .iter()
+            .map(|i| i.as_str().map(|i| i.to_string()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| malformed(id, &category["index"]))?,
+        Value::Object(index) => {
+            let mut ids = index
+                .iter()
+                .map(|(id, i)| i.as_u64().map(|i| (i, id.clone())))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| malformed(id, &category["index"]))?;
+            ids.sort();
+            ids.into_iter().map(|(_, id)| id).collect()
+        }
+        // The index may be left out when there is a single category.
+        Value::Null => match &category["label"] {
+            Value::Object(labels) if labels.len() == 1 => labels.keys().cloned().collect(),
+            _ => return Err(malformed(id, category)),
+        },
+        other => return Err(malformed(id, other)),
+    };
+
+    let labels = ids
+        .iter()
+        .map(|i| category["label"][i].as_str().unwrap_or(i).to_string())
+        .collect();
+    Ok(Dimension {
+        id: id.to_string(),
+        ids,
+        labels,
+    })
+}
+
+fn strings(json: &Value) -> Option<Vec<String>> {
+    json.as_array()?
+        .iter()
+        .map(|s| s.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+fn number<V: super::Value>(column: &str, json: &Value) -> Result<V, ParseError> {
+    let value = match json {
+        Value::Number(n) => V::parse(&n.to_string()),
+        _ => None,
+    };
+    value.ok_or_else(|| ParseError::new(ParseErrorKind::Number, column, &json.to_string()))
+}
+
+impl<V: super::Value> TimeSeriesGroup<V> {
+    /// Read a JSON-stat dataset, e.g. from StatBank or Eurostat, with one series per combination
+    /// of categories. The time dimension becomes the dates and every other dimension a tag,
+    /// except metric dimensions which only say what is counted. Missing values (`null`) are left
+    /// out.
+    pub fn from_jsonstat(tags: im::OrdSet<String>, data: &str) -> Result<Self, ParseError> {
+        let json: Value = serde_json::from_str(data).map_err(|e| malformed("", e))?;
+        // JSON-stat 1.0 wraps datasets in a bundle, e.g. `{"dataset": {...}}`, and 2.0 can embed
+        // them in a collection. Either way the first dataset is read.
+        let bundle = json.as_object().into_iter().flat_map(|b| b.values());
+        let collection = json["link"]["item"].as_array().into_iter().flatten();
+        let dataset = std::iter::once(&json)
+            .chain(collection)
+            .chain(bundle)
+            .find(|d| d["dimension"].is_object())
+            .ok_or_else(|| malformed("dimension", ""))?;
+        let dimensions = &dataset["dimension"];
+        // 1.0 keeps "id", "size" and "role" inside "dimension", 2.0 next to it.
+        let field = |name: &str| match &dataset[name] {
+            Value::Null => &dimensions[name],
+            value => value,
+        };
+
+        let ids = strings(field("id")).ok_or_else(|| malformed("id", field("id")))?;
+        let role = field("role");
+        let time_ids = strings(&role["time"]).unwrap_or_default();
+        let metric_ids = strings(&role["metric"]).unwrap_or_default();
+        let dims = ids
+            .iter()
+            .map(|id| dimension(id, &dimensions[id]))
+            .collect::<Result<Vec<_>, _>>()?;
+        let time = dims
+            .iter()
+            .position(|d| time_ids.contains(&d.id))
+            .or_else(|| {
+                dims.iter()
+                    .position(|d| ["tid", "time"].contains(&&*d.id.to_lowercase()))
+            })
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingColumn, "time", ""))?;
+
+        // Time periods are parsed from their ids, e.g. "2021K1", not from their labels.
+        let dates = dims[time]
+            .ids
+            .iter()
+            .map(|period| parse_date(&DateFormat::Auto, &dims[time].id, period))
+            .collect::<Result<Vec<NaiveDate>, _>>()?;
+
+        let sizes: Vec<usize> = dims.iter().map(|d| d.ids.len()).collect();
+        let size_ok = field("size").as_array().is_none_or(|size| {
+            size.len() == sizes.len()
+                && size
+                    .iter()
+                    .zip(&sizes)
+                    .all(|(a, &b)| a.as_u64() == Some(b as u64))
+        });
+        if !size_ok {
+            return Err(malformed("size", field("size")));
+        }
+        let count = sizes.iter().product::<usize>();
+        let values: Vec<(usize, &Value)> = match &dataset["value"] {
+            Value::Array(values) if values.len() == count => values.iter().enumerate().collect(),
+            // Sparse datasets list only the values present, keyed by their position.
+            Value::Object(values) => values
+                .iter()
+                .map(|(i, v)| match i.parse() {
+                    Ok(i) if i < count => Ok((i, v)),
+                    _ => Err(malformed("value", i)),
+                })
+                .collect::<Result<_, _>>()?,
+            other => return Err(malformed("value", other)),
+        };
+
+        let mut groups: im::OrdMap<Vec<String>, Values<V>> = im::OrdMap::new();
+        for (i, value) in values {
+            if value.is_null() {
+                continue;
+            }
+            // Values are in row-major order, the last dimension changing fastest.
+            let mut rest = i;
+            let mut position = vec![0; dims.len()];
+            for d in (0..dims.len()).rev() {
+                position[d] = rest % sizes[d];
+                rest /= sizes[d];
+            }
+            let tags = dims
+                .iter()
+                .zip(&position)
+                .enumerate()
+                .filter(|(d, (dim, _))| *d != time && !metric_ids.contains(&dim.id))
+                .map(|(_, (dim, &p))| dim.labels[p].clone())
+                .collect();
+            let points = groups.entry(tags).or_default();
+            points
+                .entry(dates[position[time]])
+                .or_default()
+                .push(number("value", value)?);
+        }
+
+        Ok(Self::new(
+            groups
+                .into_iter()
+                .map(|(values, points)| {
+                    TimeSeries::new(tags.clone().union(values.into()), aggregate(points, Aggregation::Sum))
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATASET: &str = r#"{
+        "version": "2.0",
+        "class": "dataset",
+        "id": ["OMRÅDE", "ContentsCode", "Tid"],
+        "size": [2, 1, 2],
+        "role": {"time": ["Tid"], "metric": ["ContentsCode"]},
+        "dimension": {
+            "OMRÅDE": {"category": {
+                "index": {"084": 0, "081": 1},
+                "label": {"084": "Region Hovedstaden", "081": "Region Nordjylland"}
+            }},
+            "ContentsCode": {"category": {"label": {"BEF": "Befolkning"}}},
+            "Tid": {"category": {"index": ["2021K1", "2021K2"]}}
+        },
+        "value": [1852000, 1855084.0, null, 590439]
+    }"#;
+
+    fn tags() -> im::OrdSet<String> {
+        im::OrdSet::unit("Kvartal".to_string())
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn one_series_per_category() {
+        let group: TimeSeriesGroup = TimeSeriesGroup::from_jsonstat(tags(), DATASET).unwrap();
+        let series = group.series();
+        assert_eq!(series.len(), 2);
+
+        let hovedstaden = series
+            .iter()
+            .find(|ts| ts.tags.contains("Region Hovedstaden"))
+            .unwrap();
+        assert_eq!(hovedstaden.data.get(&date("2021-01-01")), Some(&1_852_000));
+        assert_eq!(hovedstaden.data.get(&date("2021-04-01")), Some(&1_855_084));
+        // The metric only says what is counted, so it is not a tag.
+        let expected: im::OrdSet<String> =
+            vec!["Kvartal".to_string(), "Region Hovedstaden".to_string()].into();
+        assert_eq!(hovedstaden.tags, expected);
+
+        let nordjylland = series
+            .iter()
+            .find(|ts| ts.tags.contains("Region Nordjylland"))
+            .unwrap();
+        assert_eq!(nordjylland.data.len(), 1);
+        assert_eq!(nordjylland.data.get(&date("2021-04-01")), Some(&590_439));
+    }
+
+    #[test]
+    fn fractions_are_not_whole_numbers() {
+        let data = DATASET.replace("590439", "12.5");
+        let error = TimeSeriesGroup::<i64>::from_jsonstat(tags(), &data)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::Number);
+        assert_eq!(error.value, "12.5");
+
+        let group: TimeSeriesGroup<f64> = TimeSeriesGroup::from_jsonstat(tags(), &data).unwrap();
+        let nordjylland = group
+            .series()
+            .iter()
+            .find(|ts| ts.tags.contains("Region Nordjylland"))
+            .unwrap();
+        assert_eq!(nordjylland.data.get(&date("2021-04-01")), Some(&12.5));
+    }
+
+    #[test]
+    fn bundle_with_sparse_values() {
+        let bundle = r#"{"dataset": {
+            "dimension": {
+                "id": ["Tid"],
+                "size": [3],
+                "role": {"time": ["Tid"]},
+                "Tid": {"category": {"index": {"2021U01": 0, "2021U02": 1, "2021U03": 2}}}
+            },
+            "value": {"0": 10, "2": 30}
+        }}"#;
+        let group: TimeSeriesGroup = TimeSeriesGroup::from_jsonstat(tags(), bundle).unwrap();
+        let data = &group.series()[0].data;
+        assert_eq!(data.get(&date("2021-01-04")), Some(&10));
+        assert_eq!(data.get(&date("2021-01-11")), None);
+        assert_eq!(data.get(&date("2021-01-18")), Some(&30));
+    }
+}
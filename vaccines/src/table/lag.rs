@@ -0,0 +1,116 @@
+use super::{TimeSeries, Value};
+use chrono::Duration;
+
+/// Dates two series need in common for a correlation.
+const MIN_OVERLAP: usize = 14;
+
+/// How many days one series follows another, see `TimeSeries::best_lag`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lag {
+    pub days: i64,
+    /// Pearson correlation at that lag, from -1 to 1.
+    pub correlation: f64,
+}
+
+impl<V: Value> TimeSeries<V> {
+    /// Every date moved `days` later, or earlier for a negative number.
+    pub fn shift(self, days: i64) -> Self {
+        let days = Duration::days(days);
+        TimeSeries {
+            data: self
+                .data
+                .into_iter()
+                .map(|(date, value)| (date + days, value))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Pearson correlation of the values on the dates of this series that `other` has a value
+    /// for, after filling. `None` with fewer than two weeks of dates in common, or when one of
+    /// the series does not change.
+    pub fn correlation<W: Value>(&self, other: &TimeSeries<W>) -> Option<f64> {
+        let pairs: Vec<(f64, f64)> = self
+            .data
+            .keys()
+            .filter_map(|date| Some((self.value(date)?.to_f64(), other.value(date)?.to_f64())))
+            .collect();
+        if pairs.len() < MIN_OVERLAP {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for (x, y) in &pairs {
+            cov += (x - mean_x) * (y - mean_y);
+            var_x += (x - mean_x).powi(2);
+            var_y += (y - mean_y).powi(2);
+        }
+        if var_x == 0.0 || var_y == 0.0 {
+            return None;
+        }
+        Some(cov / (var_x * var_y).sqrt())
+    }
+
+    /// The lag, up to `max_days` either way, at which `later` follows this series most
+    /// closely, i.e. where `self.shift(days)` correlates best with it. Daily counts are best
+    /// compared by their 7-day averages, so the days of the week do not count.
+    pub fn best_lag<W: Value>(&self, later: &TimeSeries<W>, max_days: i64) -> Option<Lag> {
+        (-max_days..=max_days)
+            .filter_map(|days| {
+                Some(Lag {
+                    days,
+                    correlation: self.clone().shift(days).correlation(later)?,
+                })
+            })
+            .max_by(|a, b| {
+                a.correlation
+                    .partial_cmp(&b.correlation)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn start() -> NaiveDate {
+        NaiveDate::from_ymd(2021, 1, 1)
+    }
+
+    /// Values without a pattern that repeats, so only one lag fits.
+    fn cases(days: i64) -> TimeSeries {
+        let data = (0..days)
+            .map(|day| (start() + Duration::days(day), (day * 7919) % 101))
+            .collect();
+        TimeSeries::new(im::OrdSet::new(), data)
+    }
+
+    #[test]
+    fn best_lag_finds_the_shift() {
+        let admissions = cases(60).shift(5).map(|v| v / 10);
+        let lag = cases(60).best_lag(&admissions, 14).unwrap();
+        assert_eq!(lag.days, 5);
+        assert!(lag.correlation > 0.99, "{}", lag.correlation);
+
+        let earlier = cases(60).shift(-3);
+        assert_eq!(cases(60).best_lag(&earlier, 14).unwrap().days, -3);
+    }
+
+    #[test]
+    fn no_correlation_without_two_weeks_in_common() {
+        assert_eq!(cases(13).correlation(&cases(13)), None);
+        assert!(cases(14).correlation(&cases(14)).unwrap() > 0.99);
+        assert_eq!(cases(30).correlation(&cases(30).shift(20)), None);
+    }
+
+    #[test]
+    fn no_correlation_with_a_flat_series() {
+        let flat = cases(30).map(|_| 4);
+        assert_eq!(cases(30).correlation(&flat), None);
+        assert_eq!(cases(30).best_lag(&flat, 7), None);
+    }
+}
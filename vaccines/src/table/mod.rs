@@ -1,76 +1,218 @@
 use crate::web;
-use chrono::{DateTime, NaiveDate, Utc};
-use im::ordmap::Entry;
-use std::ops::Add;
-
-pub struct TimeSeriesGroup {
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+pub mod age;
+pub mod date;
+pub mod dense;
+pub mod fill;
+pub mod growth;
+pub mod jsonstat;
+pub mod lag;
+pub mod nowcast;
+pub mod ops;
+pub mod population;
+pub mod reader;
+pub mod resample;
+pub mod rolling;
+pub mod sdmx;
+pub mod validate;
+pub mod value;
+pub mod vintage;
+
+pub use age::{AgeBand, AgeBandError};
+pub use date::{DateError, DateFormat};
+pub use fill::Fill;
+pub use ops::Join;
+pub use population::Population;
+pub use reader::{Aggregation, Columns, Mode, ParseError, ParseErrorKind};
+pub use resample::Period;
+pub use value::Value;
+pub use vintage::Vintages;
+use reader::{read_rows, Row};
+
+#[derive(Clone)]
+pub struct TimeSeriesGroup<V = i64> {
     updated: DateTime<Utc>,
-    series: Vec<TimeSeries>,
+    series: Vec<TimeSeries<V>>,
+    /// Lines drawn over the series in charts, like averages and ranges, but not part of them.
+    overlay: Vec<TimeSeries<V>>,
+    /// Decimals shown in charts.
+    decimals: usize,
+    /// What the values count, e.g. "personer", shown on the axis and in the tooltips of charts.
+    unit: Option<String>,
+    /// The period of each date, for the labels of charts.
+    period: Period,
 }
 
-fn parse_date(s: &str) -> Option<NaiveDate> {
-    if s.contains('M') {
-        let mut it = s.trim_start_matches('"').trim_end_matches('"').split('M').into_iter();
-        let year = it.next()?;
-        let mut it2 = it.next()?.split('D');
-        let month = it2.next()?;
-        let day = it2.next()?;
-        Some(NaiveDate::from_ymd(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?))
-    } else {
-        let mut parts = s.split('-');
-        let year: i32 = parts.next()?.parse().ok()?;
-        let month: u32 = parts.next()?.parse().ok()?;
-        let day: u32 = parts.next()?.parse().ok()?;
-        Some(NaiveDate::from_ymd(year, month, day))
-    }
+/// Every value given for each date, e.g. by several rows of a file.
+type Values<V> = im::OrdMap<NaiveDate, Vec<V>>;
+
+/// One value per date, combined by `aggregation`.
+fn aggregate<V: Value>(values: Values<V>, aggregation: Aggregation) -> im::OrdMap<NaiveDate, V> {
+    values
+        .into_iter()
+        .filter_map(|(date, values)| Some((date, aggregation.reduce(&values)?)))
+        .collect()
 }
 
-impl TimeSeriesGroup {
-    pub fn new(series: Vec<TimeSeries>) -> Self {
-        let max_date = *series.iter().map(|ts| ts.latest_date()).max().unwrap();
+impl<V: Value> TimeSeriesGroup<V> {
+    pub fn new(series: Vec<TimeSeries<V>>) -> Self {
+        let updated = match series.iter().filter_map(|ts| ts.latest_date()).max() {
+            Some(max_date) => DateTime::from_utc(max_date.and_hms(0, 0, 0), Utc),
+            None => Utc::now(),
+        };
         TimeSeriesGroup {
-            updated: DateTime::from_utc(max_date.and_hms(0, 0, 0), Utc),
+            updated,
             series,
+            overlay: vec![],
+            decimals: V::DECIMALS,
+            unit: None,
+            period: Period::Day,
+        }
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// One series per distinct combination of the tag columns, each tagged with those values.
+    pub fn from_csv(tags: im::OrdSet<String>, data: &str, columns: &Columns) -> Result<Self, ParseError> {
+        Ok(Self::from_csv_mode(tags, data, columns, Mode::Strict)?.0)
+    }
+
+    /// `from_csv` in the given mode, also returning the rows skipped in `Mode::Lenient`.
+    pub fn from_csv_mode(
+        tags: im::OrdSet<String>,
+        data: &str,
+        columns: &Columns,
+        mode: Mode,
+    ) -> Result<(Self, Vec<ParseError>), ParseError> {
+        let (rows, skipped) = read_rows(data, columns, mode)?;
+        Ok((Self::from_rows(tags, rows, columns.aggregation), skipped))
+    }
+
+    /// A group from rows already read with `reader::read_rows`, see `from_csv`. The series
+    /// keep `aggregation` for `resample`.
+    pub fn from_rows(tags: im::OrdSet<String>, rows: Vec<Row<V>>, aggregation: Aggregation) -> Self {
+        let mut groups: im::OrdMap<Vec<String>, Values<V>> = im::OrdMap::new();
+        for row in rows {
+            let points = groups.entry(row.tags).or_default();
+            points.entry(row.date).or_default().push(row.value);
+        }
+
+        Self::new(
+            groups
+                .into_iter()
+                .map(|(values, points)| {
+                    TimeSeries::new(tags.clone().union(values.into()), aggregate(points, aggregation))
+                        .with_aggregation(aggregation)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// Sum all series into one, e.g. the national total of a group with a series per region,
+    /// on every date any of them has. Each series is filled by its policy, and with
+    /// `Fill::Zero` also after its last date, as a region without any cases is left out of
+    /// the SSI files. A date with a series still without a value is left out. Fill and
+    /// aggregation are combined like in `Add`.
+    pub fn total(self, tags: im::OrdSet<String>) -> TimeSeries<V> {
+        let dates: im::OrdSet<NaiveDate> = self
+            .series
+            .iter()
+            .flat_map(|ts| ts.data.keys())
+            .cloned()
+            .collect();
+        let value = |ts: &TimeSeries<V>, date: &NaiveDate| match ts.fill {
+            Fill::Zero => Some(ts.value(date).unwrap_or_default()),
+            _ => ts.value(date),
+        };
+        let data = dates
+            .into_iter()
+            .filter_map(|date| {
+                let sum: Option<V> = self.series.iter().map(|ts| value(ts, &date)).sum();
+                Some((date, sum?))
+            })
+            .collect();
+        let first = self.series.first();
+        let fill = match first {
+            Some(first) if self.series.iter().all(|ts| ts.fill == first.fill) => first.fill,
+            _ => Fill::Gap,
+        };
+        let aggregation = match first {
+            Some(first) if self.series.iter().all(|ts| ts.aggregation == first.aggregation) => {
+                first.aggregation
+            }
+            _ => Aggregation::default(),
+        };
+        TimeSeries {
+            tags,
+            data,
+            fill,
+            aggregation,
         }
     }
 
-    pub fn series(&self) -> &[TimeSeries] {
+    pub fn series(&self) -> &[TimeSeries<V>] {
         &self.series
     }
 
+    pub fn decimals(&self) -> usize {
+        self.decimals
+    }
+
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    pub fn overlay(&self) -> &[TimeSeries<V>] {
+        &self.overlay
+    }
+
+    /// Draw these series over the others, e.g. an average or the range of an estimate. They
+    /// are not stacked, and not part of sums like `last_sum`.
+    pub fn with_overlay(mut self, series: Vec<TimeSeries<V>>) -> Self {
+        self.overlay.extend(series);
+        self
+    }
+
     pub fn xs(&self) -> im::OrdSet<NaiveDate> {
         self.series
             .iter()
+            .chain(&self.overlay)
             .flat_map(|f| f.data.keys())
             .cloned()
             .collect()
     }
 
-    fn final_date(&self) -> NaiveDate {
-        let last_date = |ts: &TimeSeries| *ts.data.iter().last().unwrap().0;
-        self.series.iter().map(last_date).max().unwrap()
+    /// The latest date of any series, or `None` if they are all empty.
+    fn final_date(&self) -> Option<NaiveDate> {
+        self.series.iter().filter_map(|ts| ts.latest_date()).max().cloned()
     }
 
     pub fn accumulative(self) -> Self {
-        let final_date = self.final_date();
+        let final_date = match self.final_date() {
+            Some(date) => date,
+            None => return self,
+        };
         TimeSeriesGroup {
-            updated: self.updated,
             series: self
                 .series
                 .into_iter()
                 .map(|ts| ts.accumulative(final_date))
                 .collect(),
+            ..self
         }
     }
 
     pub fn diff(self) -> Self {
         TimeSeriesGroup {
-            updated: self.updated,
             series: self
                 .series
                 .into_iter()
                 .map(|ts| ts.diff())
                 .collect(),
+            ..self
         }
     }
 
@@ -78,58 +220,75 @@ impl TimeSeriesGroup {
         self.series.len()
     }
 
-    pub fn prepend(self, val: i64, start: NaiveDate, step: chrono::Duration) -> Self {
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    pub fn prepend(self, val: V, start: NaiveDate, step: chrono::Duration) -> Self {
         TimeSeriesGroup {
-            updated: self.updated,
             series: self
                 .series
                 .into_iter()
                 .map(|ts| ts.prepend(val, start, step))
                 .collect(),
+            ..self
         }
     }
 
-    pub fn last_sum(&self, start: impl Fn(&TimeSeries, &NaiveDate) -> i64) -> (NaiveDate, i64) {
-        let last_date = |ts: &TimeSeries| *ts.data.iter().last().unwrap().0;
-        let final_date = self.series.iter().map(last_date).max().unwrap();
-        let final_sum: i64 = self.series.iter().map(|x| start(x, &final_date)).sum();
-        (final_date, final_sum)
+    /// The latest date and the sum of `start` for it over the series, or `None` if they are all
+    /// empty.
+    pub fn last_sum(
+        &self,
+        start: impl Fn(&TimeSeries<V>, &NaiveDate) -> V,
+    ) -> Option<(NaiveDate, V)> {
+        let final_date = self.final_date()?;
+        let final_sum: V = self.series.iter().map(|x| start(x, &final_date)).sum();
+        Some((final_date, final_sum))
     }
 
-    pub fn out_last_sum(self, out: &mut i64) -> Self {
-        *out = self.last_sum(|ts, d| *ts.data.get(d).unwrap_or(&0)).1;
+    pub fn out_last_sum(self, out: &mut V) -> Self {
+        if let Some((_, sum)) = self.last_sum(|ts, d| ts.value(d).unwrap_or_default()) {
+            *out = sum;
+        }
         self
     }
 
     pub fn future_goal_extrapolate(
         self,
         title: &str,
-        goal: i64,
+        goal: V,
         step: chrono::Duration,
-        speed: impl Fn(&TimeSeries, &NaiveDate) -> i64,
-        start: impl Fn(&TimeSeries, &NaiveDate) -> i64,
+        speed: impl Fn(&TimeSeries<V>, &NaiveDate) -> V,
+        start: impl Fn(&TimeSeries<V>, &NaiveDate) -> V,
         end_date_out: &mut NaiveDate,
     ) -> Self {
-        let (final_date, final_sum) = self.last_sum(&start);
-        let final_speed: i64 = self.series.iter().map(|x| speed(x, &final_date)).sum();
-
-        if final_sum >= goal {
+        let (final_date, final_sum) = match self.last_sum(&start) {
+            Some(last) => last,
+            None => return self,
+        };
+        let final_speed: V = self.series.iter().map(|x| speed(x, &final_date)).sum();
+
+        // Without any progress the goal is never reached, so there is nothing to draw.
+        if final_sum >= goal || final_speed <= V::default() {
             return self
         }
 
-        *end_date_out = final_date + step * ((goal - final_sum) / final_speed) as i32;
-        return self.future_goal(title, *end_date_out, |_| goal, step, start);
+        *end_date_out = final_date + step * ((goal - final_sum) / final_speed).to_f64() as i32;
+        self.future_goal(title, *end_date_out, |_| goal, step, start)
     }
 
     pub fn future_goal(
         self,
         title: &str,
         date: NaiveDate,
-        calc_goal: impl Fn(i64) -> i64,
+        calc_goal: impl Fn(V) -> V,
         step: chrono::Duration,
-        start: impl Fn(&TimeSeries, &NaiveDate) -> i64,
+        start: impl Fn(&TimeSeries<V>, &NaiveDate) -> V,
     ) -> Self {
-        let (final_date, final_sum) = self.last_sum(&start);
+        let (final_date, final_sum) = match self.last_sum(&start) {
+            Some(last) => last,
+            None => return self,
+        };
         let goal = calc_goal(final_sum);
 
         let mut running_date = final_date;
@@ -140,7 +299,7 @@ impl TimeSeriesGroup {
             running_date += step;
 
             let days_spent = (running_date - final_date).num_days();
-            let progress = ((goal - final_sum) * days_spent) / all_days;
+            let progress = (goal - final_sum).scale(days_spent, all_days);
             goal_data.insert(running_date, final_sum + progress);
         }
 
@@ -150,97 +309,166 @@ impl TimeSeriesGroup {
             series.push(TimeSeries::new(tags, goal_data));
         }
 
+        TimeSeriesGroup { series, ..self }
+    }
+
+    /// What the values count, e.g. "personer" from the dataset in the schema.
+    pub fn with_unit(self, unit: Option<&str>) -> Self {
+        TimeSeriesGroup {
+            unit: unit.map(str::to_string),
+            ..self
+        }
+    }
+
+    /// Show values with this many decimals in charts, e.g. 1 for incidence per 100,000.
+    pub fn with_decimals(self, decimals: usize) -> Self {
+        TimeSeriesGroup { decimals, ..self }
+    }
+
+    /// Convert the values, e.g. `group.map(|v| v as f64)` to divide them later.
+    pub fn map<W: Value>(self, f: impl Fn(V) -> W + Copy) -> TimeSeriesGroup<W> {
         TimeSeriesGroup {
             updated: self.updated,
-            series,
+            series: self.series.into_iter().map(|ts| ts.map(f)).collect(),
+            overlay: self.overlay.into_iter().map(|ts| ts.map(f)).collect(),
+            decimals: W::DECIMALS,
+            unit: self.unit,
+            period: self.period,
         }
     }
 
+    /// The label of the y axis: `y` with the unit, unless it already says it like "Antal
+    /// personer...", and the date of the data.
+    fn y_label(&self, y: &str) -> String {
+        let y = match &self.unit {
+            Some(unit) if !y.to_lowercase().contains(&unit.to_lowercase()) => {
+                format!("{} ({})", y, unit)
+            }
+            _ => y.to_string(),
+        };
+        format!("{} — {}", y, self.updated.date().naive_local())
+    }
+
     pub fn plot_stacked(self, id: &str, title: &str, x: &str, y: &str) -> impl horrorshow::RenderOnce {
-        let y = format!("{} — {}", y, self.updated.date().naive_local().to_string());
+        let y = self.y_label(y);
         web::ChartGraph::bar_plot_html(id.into(), title.into(), x.into(), y, self, true)
     }
 
     pub fn plot(self, id: &str, title: &str, x: &str, y: &str) -> impl horrorshow::RenderOnce {
-        let y = format!("{} — {}", y, self.updated.date().naive_local().to_string());
+        let y = self.y_label(y);
         web::ChartGraph::bar_plot_html(id.into(), title.into(), x.into(), y, self, false)
     }
 }
 
 #[derive(Default, Clone)]
-pub struct TimeSeries {
+pub struct TimeSeries<V = i64> {
     pub tags: im::OrdSet<String>,
-    pub data: im::OrdMap<NaiveDate, i64>,
+    pub data: im::OrdMap<NaiveDate, V>,
+    /// What the dates without data mean, see `Fill`.
+    pub fill: Fill,
+    /// How the values of a week or month are combined by `resample`: added up for counts
+    /// per day, or the last one for totals.
+    pub aggregation: Aggregation,
 }
 
-impl TimeSeries {
-    pub fn new(tags: im::OrdSet<String>, data: im::OrdMap<NaiveDate, i64>) -> TimeSeries {
-        TimeSeries { tags, data }
+impl<V: Value> TimeSeries<V> {
+    pub fn new(tags: im::OrdSet<String>, data: im::OrdMap<NaiveDate, V>) -> Self {
+        TimeSeries {
+            tags,
+            data,
+            fill: Fill::default(),
+            aggregation: Aggregation::default(),
+        }
     }
 
-    pub fn from_str(tags: im::OrdSet<String>, data: &str, f: impl Fn(Vec<&str>) -> i64) -> Self {
-        let mut points = im::OrdMap::new();
+    /// How the values of a week or month are combined by `resample`.
+    pub fn with_aggregation(self, aggregation: Aggregation) -> Self {
+        TimeSeries {
+            aggregation,
+            ..self
+        }
+    }
+}
 
-        for line in data.lines() {
-            let sep = if line.contains(';') { ';' } else { ',' };
-            let mut it = line.split(sep);
-            let date = parse_date(it.next().unwrap()).or_else(|| it.next().and_then(|s| parse_date(s)));
+impl<V: Value> TimeSeries<V> {
+    /// Combine the selected value columns per date, across all other columns. To keep a column
+    /// like the region as a dimension, use `TimeSeriesGroup::from_csv` with a tag column.
+    pub fn from_csv(tags: im::OrdSet<String>, data: &str, columns: &Columns) -> Result<Self, ParseError> {
+        Ok(Self::from_csv_mode(tags, data, columns, Mode::Strict)?.0)
+    }
 
-            if let Some(d) = date {
-                let v = f(it.collect());
-                match points.entry(d) {
-                    Entry::Occupied(mut p) => *p.get_mut() = *p.get() + v,
-                    Entry::Vacant(spot) => {
-                        spot.insert(v);
-                    }
-                }
-            }
+    /// `from_csv` in the given mode, also returning the rows skipped in `Mode::Lenient`.
+    pub fn from_csv_mode(
+        tags: im::OrdSet<String>,
+        data: &str,
+        columns: &Columns,
+        mode: Mode,
+    ) -> Result<(Self, Vec<ParseError>), ParseError> {
+        let (rows, skipped) = read_rows(data, columns, mode)?;
+
+        let mut points = Values::new();
+        for row in rows {
+            points.entry(row.date).or_default().push(row.value);
         }
 
-        Self::new(tags, points)
+        let ts = Self::new(tags, aggregate(points, columns.aggregation));
+        Ok((ts.with_aggregation(columns.aggregation), skipped))
     }
+}
 
-    pub fn latest_date(&self) -> &NaiveDate {
-        self.data.keys().max().unwrap()
+impl<V: Value> TimeSeries<V> {
+    pub fn latest_date(&self) -> Option<&NaiveDate> {
+        self.data.keys().max()
     }
 
+    /// Running total, with the missing dates filled first. A gap adds nothing to the total but
+    /// gets no value itself. With `Fill::Zero` nothing more happens after the last date, so
+    /// the total is carried on until `final_date`.
     pub fn accumulative(self, final_date: NaiveDate) -> Self {
-        let init = (0i64, im::OrdMap::new());
+        let filled = self.filled();
+        let init = (V::default(), im::OrdMap::new());
 
-        let (total, mut data) = self
+        let (total, mut data) = filled
             .data
             .into_iter()
             .fold(init, |(running_total, out), (t, y)| {
                 ((y + running_total), out.update(t, y + running_total))
             });
 
-        if !data.contains_key(&final_date) {
+        if filled.fill == Fill::Zero && !data.contains_key(&final_date) {
             data.insert(final_date, total);
         }
 
         TimeSeries {
-            tags: self.tags,
+            tags: filled.tags,
             data,
+            fill: filled.fill,
+            aggregation: Aggregation::Last,
         }
     }
 
+    /// The change since the date one step before, with that date filled. There is no change
+    /// after a gap. The changes are added up when resampled.
     pub fn diff(self) -> Self {
-        let init = (*self.data.iter().next().unwrap().1, im::OrdMap::new());
-        let (_prev, data) = self
+        let step = Duration::days(self.step().unwrap_or(1));
+        let data = self
             .data
-            .into_iter()
+            .iter()
             .skip(1)
-            .fold(init, |(prev, out), (t, y)| {
-                (y, out.update(t, y - prev))
-            });
+            .filter_map(|(t, y)| Some((*t, *y - self.value(&(*t - step))?)))
+            .collect();
         TimeSeries {
-            tags: self.tags,
-            data
+            data,
+            aggregation: Aggregation::Sum,
+            ..self
         }
     }
 
-    pub fn prepend(self, val: i64, start: NaiveDate, step: chrono::Duration) -> Self {
-        let mut current = *self.data.keys().min().unwrap();
+    pub fn prepend(self, val: V, start: NaiveDate, step: chrono::Duration) -> Self {
+        let mut current = match self.data.keys().next() {
+            Some(first) => *first,
+            None => return self,
+        };
         let mut new_points = im::OrdMap::new();
 
         while current > start {
@@ -249,19 +477,151 @@ impl TimeSeries {
         }
 
         TimeSeries {
-            tags: self.tags,
             data: new_points.union(self.data),
+            ..self
         }
     }
-}
-
-impl Add for TimeSeries {
-    type Output = TimeSeries;
 
-    fn add(self, rhs: Self) -> Self::Output {
+    pub fn map<W: Value>(self, f: impl Fn(V) -> W) -> TimeSeries<W> {
         TimeSeries {
-            tags: self.tags.union(rhs.tags),
-            data: self.data.union_with(rhs.data, std::ops::Add::add),
+            tags: self.tags,
+            data: self.data.into_iter().map(|(date, v)| (date, f(v))).collect(),
+            fill: self.fill,
+            aggregation: self.aggregation,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn series(values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit("a".to_string()), data)
+    }
+
+    #[test]
+    fn empty_series_are_left_as_they_are() {
+        let empty = TimeSeriesGroup::new(vec![series(&[])]);
+        assert_eq!(empty.last_sum(|_, _| 1), None);
+        let group = empty
+            .prepend(0, date("2021-01-01"), Duration::days(1))
+            .accumulative()
+            .diff();
+        assert!(group.series()[0].data.is_empty());
+    }
+
+    #[test]
+    fn no_goal_without_progress() {
+        let group = TimeSeriesGroup::new(vec![series(&[("2021-01-01", 5), ("2021-01-02", 5)])]);
+        let mut end = date("2000-01-01");
+        let group = group.future_goal_extrapolate(
+            "Mål",
+            100,
+            Duration::days(1),
+            |_, _| 0,
+            |ts, d| ts.data[d],
+            &mut end,
+        );
+        assert_eq!(group.len(), 1);
+        assert_eq!(end, date("2000-01-01"));
+    }
+
+    #[test]
+    fn one_series_per_region_and_their_total() {
+        let data = "Dato;Regionsnavn;Antal\n\
+                    2021-01-01;Nord;1\n\
+                    2021-01-01;Syd;2\n\
+                    2021-01-02;Syd;3\n";
+        let columns = Columns::new("Dato", "Antal").tag("Regionsnavn");
+        let group: TimeSeriesGroup =
+            TimeSeriesGroup::from_csv(im::OrdSet::new(), data, &columns).unwrap();
+        assert_eq!(group.len(), 2);
+        assert_eq!(group.series()[0].tags, im::OrdSet::unit("Nord".to_string()));
+
+        // Nord has no cases on the second day, so it is left out of the file.
+        let total = group
+            .with_fill(Fill::Zero)
+            .total(im::OrdSet::unit("I alt".to_string()));
+        assert_eq!(total.data[&date("2021-01-01")], 3);
+        assert_eq!(total.data[&date("2021-01-02")], 3);
+    }
+
+    #[test]
+    fn unit_on_the_axis_unless_it_is_there() {
+        let group = || TimeSeriesGroup::new(vec![series(&[("2021-01-01", 1)])]);
+        let people = group().with_unit(Some("personer"));
+        assert_eq!(
+            people.y_label("Smittede"),
+            "Smittede (personer) — 2021-01-01"
+        );
+        assert_eq!(
+            people.y_label("Antal Personer"),
+            "Antal Personer — 2021-01-01"
+        );
+        assert_eq!(group().y_label("Smittede"), "Smittede — 2021-01-01");
+    }
+
+    fn region(tag: &str, fill: Fill, values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit(tag.to_string()), data).with_fill(fill)
+    }
+
+    #[test]
+    fn total_keeps_dates_after_a_region_ends() {
+        let group = TimeSeriesGroup::new(vec![
+            region(
+                "Hovedstaden",
+                Fill::Zero,
+                &[("2021-06-20", 5), ("2021-06-21", 6), ("2021-06-24", 7)],
+            ),
+            region(
+                "Nordjylland",
+                Fill::Zero,
+                &[("2021-06-20", 1), ("2021-06-22", 2)],
+            ),
+        ]);
+        let total = group.total(im::OrdSet::unit("I alt".to_string()));
+        let expected: im::OrdMap<NaiveDate, i64> = vec![
+            (date("2021-06-20"), 6),
+            (date("2021-06-21"), 6),
+            (date("2021-06-22"), 2),
+            (date("2021-06-24"), 7),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(total.data, expected);
+        assert_eq!(total.fill, Fill::Zero);
+        assert_eq!(total.tags, im::OrdSet::unit("I alt".to_string()));
+    }
+
+    #[test]
+    fn total_leaves_out_unknown_dates() {
+        let group = TimeSeriesGroup::new(vec![
+            region(
+                "a",
+                Fill::Gap,
+                &[("2021-01-01", 1), ("2021-01-02", 2), ("2021-01-03", 3)],
+            ),
+            region("b", Fill::Gap, &[("2021-01-01", 10), ("2021-01-03", 30)]),
+        ]);
+        let total = group.total(im::OrdSet::new());
+        assert_eq!(
+            total.data.keys().cloned().collect::<Vec<_>>(),
+            vec![date("2021-01-01"), date("2021-01-03")]
+        );
+        assert_eq!(total.data[&date("2021-01-03")], 33);
+    }
+
+    #[test]
+    fn total_of_nothing_is_empty() {
+        let total: TimeSeries = TimeSeriesGroup::new(vec![]).total(im::OrdSet::new());
+        assert!(total.data.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use super::vintage::Triangle;
+use super::{TimeSeries, TimeSeriesGroup};
+use chrono::{Duration, NaiveDate};
+
+/// How much to scale up a value reported some days after its date to get the eventual value,
+/// with a range from the 10th to the 90th percentile of past revisions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Factor {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Reporting delay, as a factor per number of days between a date and the release. Values
+/// reported at least `factors.len()` days after their date are taken as final, and so are
+/// values at delays without a factor.
+#[derive(Clone, Debug, Default)]
+pub struct Delays {
+    pub factors: Vec<Option<Factor>>,
+}
+
+/// A series with its recent days corrected for reporting delay, and the range of the correction.
+pub struct Nowcast {
+    pub estimate: TimeSeries,
+    pub low: TimeSeries,
+    pub high: TimeSeries,
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (below, above) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+    below + (above - below) * rank.fract()
+}
+
+impl Triangle {
+    /// Estimate the reporting delay from dates whose value has settled, i.e. dates with a value
+    /// reported `settled` days or more after them. Delays without any reported value on such a
+    /// date get no factor and are not corrected.
+    pub fn delays(&self, settled: i64) -> Delays {
+        let finals: Vec<(&im::OrdMap<i64, i64>, i64)> = self
+            .rows
+            .values()
+            .filter_map(|row| {
+                let (delay, value) = row.iter().next_back()?;
+                Some((row, *value)).filter(|_| *delay >= settled)
+            })
+            .collect();
+
+        let factors = (0..settled)
+            .map(|delay| {
+                let reported: Vec<(i64, i64)> = finals
+                    .iter()
+                    .filter_map(|(row, last)| row.get(&delay).map(|v| (*v, *last)))
+                    .collect();
+                let total: i64 = reported.iter().map(|(v, _)| v).sum();
+                if total <= 0 {
+                    return None;
+                }
+                let mut ratios: Vec<f64> = reported
+                    .iter()
+                    .filter(|(v, _)| *v > 0)
+                    .map(|(v, last)| *last as f64 / *v as f64)
+                    .collect();
+                ratios.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let last: i64 = reported.iter().map(|(_, last)| last).sum();
+                Some(Factor {
+                    estimate: last as f64 / total as f64,
+                    low: percentile(&ratios, 0.1),
+                    high: percentile(&ratios, 0.9),
+                })
+            })
+            .collect();
+        Delays { factors }
+    }
+}
+
+impl Delays {
+    /// The number of delays with a factor.
+    pub fn len(&self) -> usize {
+        self.factors.iter().filter(|f| f.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.factors.iter().all(|f| f.is_none())
+    }
+
+    fn factor(&self, delay: i64) -> Option<Factor> {
+        if delay < 0 {
+            return None;
+        }
+        self.factors.get(delay as usize).cloned().flatten()
+    }
+}
+
+impl TimeSeries {
+    /// Scale up the days reported less than `delays.factors.len()` days before `published`.
+    /// The low and high series only have the corrected days.
+    pub fn nowcast(&self, published: NaiveDate, delays: &Delays) -> Nowcast {
+        let mut estimate = self.data.clone();
+        let mut low = im::OrdMap::new();
+        let mut high = im::OrdMap::new();
+        for (date, value) in &self.data {
+            if let Some(factor) = delays.factor((published - *date).num_days()) {
+                let scaled = |f: f64| (*value as f64 * f).round() as i64;
+                estimate.insert(*date, scaled(factor.estimate));
+                low.insert(*date, scaled(factor.low));
+                high.insert(*date, scaled(factor.high));
+            }
+        }
+        let range = |name: &str, data| TimeSeries::new(self.tags.update(name.to_string()), data);
+        Nowcast {
+            estimate: TimeSeries::new(self.tags.clone(), estimate).with_fill(self.fill),
+            low: range("Nedre grænse", low),
+            high: range("Øvre grænse", high),
+        }
+    }
+}
+
+impl TimeSeriesGroup {
+    /// Correct every series for reporting delay, taking the data as published on `published`,
+    /// the date of the snapshot it is from. Without one, or if the data goes past it, the data
+    /// is taken as published the day after its last date like the SSI files. The ranges are
+    /// stored in `range` to be drawn with `with_overlay` once goals have been drawn from the
+    /// corrected numbers.
+    pub fn nowcast(
+        self,
+        published: Option<NaiveDate>,
+        delays: &Delays,
+        range: &mut Vec<TimeSeries>,
+    ) -> Self {
+        let final_date = match self.final_date() {
+            Some(date) if !delays.is_empty() => date,
+            _ => return self,
+        };
+        let published = published
+            .filter(|published| *published >= final_date)
+            .unwrap_or(final_date + Duration::days(1));
+        let series = self
+            .series
+            .into_iter()
+            .map(|ts| {
+                let nowcast = ts.nowcast(published, delays);
+                if !nowcast.low.data.is_empty() {
+                    range.push(nowcast.low);
+                    range.push(nowcast.high);
+                }
+                nowcast.estimate
+            })
+            .collect();
+        TimeSeriesGroup { series, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Vintages;
+    use super::*;
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd(2021, 1, 1) + Duration::days(n)
+    }
+
+    /// Half of each day is reported on the day, 80% the day after and all of it after that.
+    fn reported(published: i64, days: i64) -> TimeSeries {
+        let data = (0..=days)
+            .map(|date| {
+                let value = match published - date {
+                    0 => 50,
+                    1 => 80,
+                    _ => 100,
+                };
+                (day(date), value)
+            })
+            .collect();
+        TimeSeries::new(im::OrdSet::unit("a".to_string()), data)
+    }
+
+    fn delays() -> Delays {
+        let releases = (0..20).map(|p| (day(p), reported(p, p))).collect();
+        Vintages::new(releases).triangle(28).delays(3)
+    }
+
+    #[test]
+    fn delays_from_settled_dates() {
+        let delays = delays();
+        let estimates: Vec<f64> = delays.factors.iter().map(|f| f.unwrap().estimate).collect();
+        assert_eq!(estimates, vec![2.0, 1.25, 1.0]);
+        assert_eq!(delays.len(), 3);
+        assert!(!delays.is_empty());
+
+        let unknown = Delays {
+            factors: vec![None, None],
+        };
+        assert_eq!(unknown.len(), 0);
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn nowcast_from_the_publication_date() {
+        let group = || TimeSeriesGroup::new(vec![reported(10, 10)]);
+        let last = |group: TimeSeriesGroup| group.series()[0].data.get(&day(10)).cloned();
+
+        let mut range = vec![];
+        let nowcast = group().nowcast(Some(day(10)), &delays(), &mut range);
+        assert_eq!(last(nowcast), Some(100));
+        assert_eq!(range.len(), 2);
+
+        // Taken as published the day after, so the last day is scaled as a day old.
+        let nowcast = group().nowcast(None, &delays(), &mut vec![]);
+        assert_eq!(last(nowcast), Some(63));
+    }
+}
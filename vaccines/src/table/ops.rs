@@ -0,0 +1,290 @@
+//! Arithmetic on series, date by date. Both series are filled by their own fill policy first;
+//! a `Join` decides which dates the result has and what a side still without a value counts
+//! as. The operator `+` uses `Join::Outer` with zero, like a total, and `-`, `*` and `/` use
+//! `Join::Inner`.
+//!
+//! The result has the tags of the left series, as in "started − done" or "positives / tests",
+//! except for a sum, which has the tags of both. It keeps the fill policy and aggregation if
+//! both series have the same, otherwise its missing dates are gaps and it is added up by
+//! `resample`.
+
+use super::{Aggregation, Fill, TimeSeries, Value};
+use chrono::NaiveDate;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// Which dates a combination of two series has.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Join<V> {
+    /// Only dates both series have a value for.
+    Inner,
+    /// Dates either series has a value for. The other side counts as the given value.
+    Outer(V),
+    /// Dates the left series has a value for. The right side counts as the given value.
+    Left(V),
+}
+
+impl<V: Value> TimeSeries<V> {
+    /// `f` of the values of both series, on the dates picked by `join`.
+    pub fn zip_with(self, rhs: Self, join: Join<V>, f: impl Fn(V, V) -> Option<V>) -> Self {
+        let dates: im::OrdSet<NaiveDate> = match join {
+            Join::Left(_) => self.data.keys().cloned().collect(),
+            _ => self.data.keys().chain(rhs.data.keys()).cloned().collect(),
+        };
+        let data = dates
+            .into_iter()
+            .filter_map(|date| {
+                let (left, right) = (self.value(&date), rhs.value(&date));
+                let (left, right) = match join {
+                    Join::Inner => (left?, right?),
+                    Join::Outer(_) if left.is_none() && right.is_none() => return None,
+                    Join::Outer(fill) => (left.unwrap_or(fill), right.unwrap_or(fill)),
+                    Join::Left(fill) => (left?, right.unwrap_or(fill)),
+                };
+                Some((date, f(left, right)?))
+            })
+            .collect();
+        let fill = if self.fill == rhs.fill {
+            self.fill
+        } else {
+            Fill::Gap
+        };
+        let aggregation = if self.aggregation == rhs.aggregation {
+            self.aggregation
+        } else {
+            Aggregation::default()
+        };
+        TimeSeries {
+            tags: self.tags,
+            data,
+            fill,
+            aggregation,
+        }
+    }
+
+    pub fn add_with(self, rhs: Self, join: Join<V>) -> Self {
+        let tags = self.tags.clone().union(rhs.tags.clone());
+        TimeSeries {
+            tags,
+            ..self.zip_with(rhs, join, |a, b| Some(a + b))
+        }
+    }
+
+    pub fn sub_with(self, rhs: Self, join: Join<V>) -> Self {
+        self.zip_with(rhs, join, |a, b| Some(a - b))
+    }
+
+    pub fn mul_with(self, rhs: Self, join: Join<V>) -> Self {
+        self.zip_with(rhs, join, |a, b| Some(a * b))
+    }
+
+    /// Dates where the right side is zero are left out. Whole numbers are rounded down, so
+    /// use `map(Value::to_f64)` first for fractions like test positivity.
+    pub fn div_with(self, rhs: Self, join: Join<V>) -> Self {
+        self.zip_with(rhs, join, |a, b| {
+            if b == V::default() {
+                None
+            } else {
+                Some(a / b)
+            }
+        })
+    }
+}
+
+impl<V: Value> Add for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.add_with(rhs, Join::Outer(V::default()))
+    }
+}
+
+impl<V: Value> Sub for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.sub_with(rhs, Join::Inner)
+    }
+}
+
+impl<V: Value> Mul for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.mul_with(rhs, Join::Inner)
+    }
+}
+
+impl<V: Value> Div for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.div_with(rhs, Join::Inner)
+    }
+}
+
+/// The value added to every date of the series.
+impl<V: Value> Add<V> for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn add(self, rhs: V) -> Self::Output {
+        self.map(|v| v + rhs)
+    }
+}
+
+impl<V: Value> Sub<V> for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn sub(self, rhs: V) -> Self::Output {
+        self.map(|v| v - rhs)
+    }
+}
+
+/// E.g. `* 100` for percent.
+impl<V: Value> Mul<V> for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn mul(self, rhs: V) -> Self::Output {
+        self.map(|v| v * rhs)
+    }
+}
+
+/// Dividing by zero leaves no dates.
+impl<V: Value> Div<V> for TimeSeries<V> {
+    type Output = TimeSeries<V>;
+
+    fn div(self, rhs: V) -> Self::Output {
+        if rhs == V::default() {
+            return TimeSeries {
+                data: im::OrdMap::new(),
+                ..self
+            };
+        }
+        self.map(|v| v / rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn series(tag: &str, fill: Fill, values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit(tag.to_string()), data).with_fill(fill)
+    }
+
+    fn dates(ts: &TimeSeries) -> Vec<(NaiveDate, i64)> {
+        ts.data.iter().map(|(d, v)| (*d, *v)).collect()
+    }
+
+    fn started() -> TimeSeries {
+        series(
+            "Første",
+            Fill::Gap,
+            &[("2021-01-01", 10), ("2021-01-02", 20), ("2021-01-04", 40)],
+        )
+    }
+
+    fn done() -> TimeSeries {
+        series(
+            "Færdig",
+            Fill::Gap,
+            &[("2021-01-02", 5), ("2021-01-03", 6), ("2021-01-04", 8)],
+        )
+    }
+
+    #[test]
+    fn inner_join_only_where_both_are_known() {
+        let left = started().sub_with(done(), Join::Inner);
+        assert_eq!(
+            dates(&left),
+            vec![(date("2021-01-02"), 15), (date("2021-01-04"), 32)]
+        );
+        assert_eq!(left.tags, im::OrdSet::unit("Første".to_string()));
+    }
+
+    #[test]
+    fn outer_join_counts_the_missing_side() {
+        let sum = started().add_with(done(), Join::Outer(0));
+        assert_eq!(
+            dates(&sum),
+            vec![
+                (date("2021-01-01"), 10),
+                (date("2021-01-02"), 25),
+                (date("2021-01-03"), 6),
+                (date("2021-01-04"), 48),
+            ]
+        );
+        assert_eq!(sum.tags.len(), 2);
+    }
+
+    #[test]
+    fn left_join_keeps_the_dates_of_the_left() {
+        let left = started().sub_with(done(), Join::Left(0));
+        assert_eq!(
+            dates(&left),
+            vec![
+                (date("2021-01-01"), 10),
+                (date("2021-01-02"), 15),
+                (date("2021-01-04"), 32),
+            ]
+        );
+    }
+
+    #[test]
+    fn sides_are_filled_first() {
+        let zero = series("a", Fill::Zero, &[("2021-01-01", 1), ("2021-01-03", 3)]);
+        let forward = series(
+            "b",
+            Fill::Forward,
+            &[("2021-01-01", 10), ("2021-01-03", 30)],
+        );
+        let sum = zero + forward;
+        assert_eq!(sum.data.get(&date("2021-01-02")), None);
+        assert_eq!(sum.data.get(&date("2021-01-03")), Some(&33));
+        assert_eq!(sum.fill, Fill::Gap);
+
+        let zero = series("a", Fill::Zero, &[("2021-01-01", 1), ("2021-01-03", 3)]);
+        let gaps = series("b", Fill::Gap, &[("2021-01-01", 10), ("2021-01-02", 20)]);
+        // A side without a value, even after filling, counts as zero.
+        assert_eq!(
+            dates(&(zero + gaps)),
+            vec![
+                (date("2021-01-01"), 11),
+                (date("2021-01-02"), 20),
+                (date("2021-01-03"), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn sum_of_bands_of_different_lengths() {
+        // The younger band was only published from the second week.
+        let old = series(
+            "60-69",
+            Fill::Gap,
+            &[("2021-01-04", 10), ("2021-01-11", 20), ("2021-01-18", 30)],
+        );
+        let young = series("0-9", Fill::Gap, &[("2021-01-11", 1), ("2021-01-18", 2)]);
+        let sum = old + young;
+        assert_eq!(
+            dates(&sum),
+            vec![
+                (date("2021-01-04"), 10),
+                (date("2021-01-11"), 21),
+                (date("2021-01-18"), 32),
+            ]
+        );
+        assert_eq!(sum.tags.len(), 2);
+    }
+
+    #[test]
+    fn dividing_by_zero_leaves_out_the_date() {
+        let tests = series("b", Fill::Gap, &[("2021-01-02", 0), ("2021-01-04", 4)]);
+        assert_eq!(dates(&(started() / tests)), vec![(date("2021-01-04"), 10)]);
+        assert!((started() / 0).data.is_empty());
+    }
+}
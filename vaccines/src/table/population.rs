@@ -0,0 +1,208 @@
+use super::{AgeBand, TimeSeries, TimeSeriesGroup, Value};
+use std::fmt;
+
+/// Tags for everyone rather than an area or an age band, like the totals in FOLK1A.
+const TOTALS: &[&str] = &["Hele landet", "I alt", "Alder i alt", "Total"];
+
+/// Inhabitants by area and age band, e.g. from StatBank table FOLK1A split by OMRÅDE and
+/// ALDER, to compare regions and age bands of different size.
+#[derive(Clone, Debug, Default)]
+pub struct Population {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    /// Area names, without "Region " so they match the SSI files.
+    tags: im::OrdSet<String>,
+    age: Option<AgeBand>,
+    count: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PopulationError {
+    /// No population matches the tags of this series.
+    Unmatched(String),
+}
+
+impl fmt::Display for PopulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PopulationError::Unmatched(series) => write!(f, "no population for {:?}", series),
+        }
+    }
+}
+
+impl std::error::Error for PopulationError {}
+
+impl Population {
+    /// The newest count of each series in `group`, by its tags other than `ignore`. Tags like
+    /// "50 år" or "50-59" are the age band, and totals like "Hele landet" are left out, so the
+    /// national count has no tags.
+    pub fn new(group: &TimeSeriesGroup, ignore: &im::OrdSet<String>) -> Self {
+        let entries = group
+            .series()
+            .iter()
+            .filter_map(|ts| {
+                let count = *ts.data.values().next_back()?;
+                let age = ts.tags.iter().find_map(|t| t.parse().ok());
+                let tags = ts
+                    .tags
+                    .iter()
+                    .filter(|t| !ignore.contains(*t) && !TOTALS.contains(&t.as_str()))
+                    .filter(|t| t.parse::<AgeBand>().is_err())
+                    .map(|t| t.trim_start_matches("Region ").to_string())
+                    .collect();
+                Some(Entry { tags, age, count })
+            })
+            .collect();
+        Population { entries }
+    }
+
+    /// The population for a series with these tags: the most specific area whose tags are all
+    /// among them, summed over the ages in the series' age band. Without an age band, the
+    /// total for all ages. A series without any known area, like a national total, gets the
+    /// national count.
+    pub fn get(&self, tags: &im::OrdSet<String>) -> Option<i64> {
+        let band: Option<AgeBand> = tags.iter().find_map(|t| t.parse().ok());
+        let in_area = |e: &&Entry| e.tags.iter().all(|t| tags.contains(t));
+        let mut matching: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(in_area)
+            .filter(|e| match (band, e.age) {
+                (Some(band), Some(age)) => band.contains(&age),
+                (Some(_), None) => false,
+                (None, age) => age.is_none(),
+            })
+            .collect();
+        if matching.is_empty() && band.is_none() {
+            // Only counts by age, so add them up.
+            matching = self.entries.iter().filter(in_area).collect();
+        }
+
+        let area = &matching.iter().max_by_key(|e| e.tags.len())?.tags;
+        Some(
+            matching
+                .iter()
+                .filter(|e| e.tags == *area)
+                .map(|e| e.count)
+                .sum(),
+        )
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// Every series divided by the population matching its tags, see `Population::get`, and
+    /// multiplied by `per`: 100_000.0 for per 100,000 inhabitants or 100.0 for percent.
+    pub fn per_capita(
+        self,
+        population: &Population,
+        per: f64,
+    ) -> Result<TimeSeriesGroup<f64>, PopulationError> {
+        let divide = |all: Vec<TimeSeries<V>>| {
+            all.into_iter()
+                .map(|ts| {
+                    let count = population.get(&ts.tags).ok_or_else(|| {
+                        PopulationError::Unmatched(
+                            ts.tags.iter().cloned().collect::<Vec<_>>().join(","),
+                        )
+                    })?;
+                    Ok(ts.map(|v| v.to_f64() * per / count as f64))
+                })
+                .collect::<Result<_, _>>()
+        };
+        Ok(TimeSeriesGroup {
+            updated: self.updated,
+            series: divide(self.series)?,
+            overlay: divide(self.overlay)?,
+            decimals: f64::DECIMALS,
+            // No longer a count of the unit.
+            unit: None,
+            period: self.period,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::TimeSeries;
+    use chrono::NaiveDate;
+
+    fn tags(tags: &[&str]) -> im::OrdSet<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn counts(counts: &[(&[&str], i64)]) -> TimeSeriesGroup {
+        let date = NaiveDate::from_ymd(2021, 4, 1);
+        TimeSeriesGroup::new(
+            counts
+                .iter()
+                .map(|(t, count)| TimeSeries::new(tags(t), im::OrdMap::unit(date, *count)))
+                .collect(),
+        )
+    }
+
+    /// FOLK1A by region and single years of age, with the totals StatBank adds.
+    fn folk1a() -> Population {
+        let group = counts(&[
+            (&["Befolkning", "Hele landet", "Alder i alt"], 1000),
+            (&["Befolkning", "Region Hovedstaden", "Alder i alt"], 300),
+            (&["Befolkning", "Region Hovedstaden", "60 år"], 20),
+            (&["Befolkning", "Region Hovedstaden", "61 år"], 30),
+            (&["Befolkning", "Region Nordjylland", "Alder i alt"], 100),
+        ]);
+        Population::new(&group, &tags(&["Befolkning"]))
+    }
+
+    #[test]
+    fn most_specific_area() {
+        let population = folk1a();
+        assert_eq!(
+            population.get(&tags(&["Hovedstaden", "Smittede"])),
+            Some(300)
+        );
+        assert_eq!(population.get(&tags(&["Nordjylland"])), Some(100));
+        // No known area, like a national total or a region not in the table.
+        assert_eq!(population.get(&tags(&["Smittede"])), Some(1000));
+        assert_eq!(population.get(&tags(&["Sjælland"])), Some(1000));
+    }
+
+    #[test]
+    fn summed_over_the_age_band() {
+        let population = folk1a();
+        assert_eq!(population.get(&tags(&["Hovedstaden", "60-69"])), Some(50));
+        assert_eq!(population.get(&tags(&["Hovedstaden", "61 år"])), Some(30));
+        // Not known by age for Nordjylland, and no ages 0-9 at all.
+        assert_eq!(population.get(&tags(&["Nordjylland", "60-69"])), None);
+        assert_eq!(population.get(&tags(&["Hovedstaden", "0-9"])), None);
+    }
+
+    #[test]
+    fn ages_are_added_up_without_a_total() {
+        let group = counts(&[(&["0-59"], 70), (&["60+"], 30)]);
+        let population = Population::new(&group, &im::OrdSet::new());
+        assert_eq!(population.get(&tags(&["Smittede"])), Some(100));
+        assert_eq!(population.get(&tags(&["60+"])), Some(30));
+    }
+
+    #[test]
+    fn per_capita_in_percent() {
+        let vaccinated = counts(&[(&["Hovedstaden"], 150), (&["Nordjylland"], 25)]);
+        let percent = vaccinated.per_capita(&folk1a(), 100.0).unwrap();
+        let values: Vec<f64> = percent
+            .series()
+            .iter()
+            .map(|ts| *ts.data.values().next().unwrap())
+            .collect();
+        assert_eq!(values, vec![50.0, 25.0]);
+        assert_eq!(percent.decimals(), f64::DECIMALS);
+
+        let by_age = counts(&[(&["Hovedstaden", "0-9"], 1)]);
+        assert_eq!(
+            by_age.per_capita(&folk1a(), 100.0).err(),
+            Some(PopulationError::Unmatched("0-9,Hovedstaden".to_string()))
+        );
+    }
+}
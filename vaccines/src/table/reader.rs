@@ -0,0 +1,481 @@
+use super::date::{DateError, DateFormat};
+use super::Value;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::fmt;
+
+/// How rows with the same date (and tags) are combined, and the values of a period when a
+/// series is resampled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    #[default]
+    Sum,
+    Last,
+    Min,
+    Max,
+    /// Rounded for whole numbers.
+    Mean,
+}
+
+impl Aggregation {
+    /// Combine `values`, in the order they were given, or `None` without any.
+    pub fn reduce<V: Value>(self, values: &[V]) -> Option<V> {
+        let (&first, rest) = values.split_first()?;
+        let pick = |better: fn(&V, &V) -> bool| {
+            rest.iter().fold(first, |kept, &v| if better(&v, &kept) { v } else { kept })
+        };
+        Some(match self {
+            Aggregation::Sum => values.iter().cloned().sum(),
+            Aggregation::Last => *values.last().unwrap(),
+            Aggregation::Min => pick(|v, kept| v < kept),
+            Aggregation::Max => pick(|v, kept| v > kept),
+            Aggregation::Mean => {
+                V::from_f64(values.iter().map(|v| v.to_f64()).sum::<f64>() / values.len() as f64)
+            }
+        })
+    }
+}
+
+/// Selects the columns of a CSV file by their header name.
+#[derive(Clone, Debug)]
+pub struct Columns {
+    pub date: String,
+    pub date_format: DateFormat,
+    pub values: Vec<String>,
+    pub tags: Vec<String>,
+    pub aggregation: Aggregation,
+    /// Values that mean "no data", like ".." in StatBank files. Rows with them are left out.
+    pub missing: Vec<String>,
+    /// Values are written with a decimal point, like "1234.0", and rounded to whole numbers.
+    pub decimals: bool,
+}
+
+impl Columns {
+    pub fn new(date: &str, value: &str) -> Self {
+        Columns {
+            date: date.to_string(),
+            date_format: DateFormat::Auto,
+            values: vec![value.to_string()],
+            tags: vec![],
+            aggregation: Aggregation::Sum,
+            missing: vec![],
+            decimals: false,
+        }
+    }
+
+    /// Add another value column. The values of a row are added up.
+    pub fn value(mut self, column: &str) -> Self {
+        self.values.push(column.to_string());
+        self
+    }
+
+    /// Add a column whose value is attached to every row as a tag, e.g. "Regionsnavn".
+    pub fn tag(mut self, column: &str) -> Self {
+        self.tags.push(column.to_string());
+        self
+    }
+
+    pub fn date_format(self, date_format: DateFormat) -> Self {
+        Columns {
+            date_format,
+            ..self
+        }
+    }
+
+    pub fn aggregate(self, aggregation: Aggregation) -> Self {
+        Columns {
+            aggregation,
+            ..self
+        }
+    }
+
+    pub fn missing(mut self, value: &str) -> Self {
+        self.missing.push(value.to_string());
+        self
+    }
+
+    pub fn decimals(self) -> Self {
+        Columns {
+            decimals: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Row<V = i64> {
+    pub date: NaiveDate,
+    pub value: V,
+    pub tags: Vec<String>,
+}
+
+/// A date that appears in more than one row of the same series, e.g. a release where SSI
+/// repeated a day. The rows are combined as usual, so this is only reported.
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    pub file: Option<String>,
+    pub tags: Vec<String>,
+    pub date: NaiveDate,
+    pub rows: usize,
+}
+
+/// `Strict` fails on the first bad row, `Lenient` skips it and reports it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Strict,
+    Lenient,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingColumn,
+    Malformed,
+    Date,
+    InvalidDate,
+    Number,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub file: Option<String>,
+    pub line: u64,
+    pub column: String,
+    pub value: String,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, column: impl ToString, value: &str) -> Self {
+        ParseError {
+            kind,
+            file: None,
+            line: 0,
+            column: column.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn at_line(self, line: u64) -> Self {
+        ParseError { line, ..self }
+    }
+
+    pub fn in_file(self, file: &str) -> Self {
+        ParseError {
+            file: Some(file.to_string()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::MissingColumn => "missing column",
+            ParseErrorKind::Malformed => "malformed row",
+            ParseErrorKind::Date => "unrecognised date",
+            ParseErrorKind::InvalidDate => "no such date",
+            ParseErrorKind::Number => "invalid number",
+        };
+        write!(
+            f,
+            "{}:{}: {} in column {:?}: {:?}",
+            self.file.as_deref().unwrap_or("<data>"),
+            self.line,
+            what,
+            self.column,
+            self.value
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a single cell as a number, e.g. a count of vaccinations or an incidence like "12.5".
+pub fn parse_number<V: Value>(column: impl ToString, value: &str) -> Result<V, ParseError> {
+    V::parse(value.trim()).ok_or_else(|| ParseError::new(ParseErrorKind::Number, column, value))
+}
+
+/// Parse a single cell with a decimal point, e.g. "1234.0", rounded for whole numbers.
+pub fn parse_decimal<V: Value>(column: impl ToString, value: &str) -> Result<V, ParseError> {
+    match value.trim().parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(V::from_f64(v)),
+        _ => Err(ParseError::new(ParseErrorKind::Number, column, value)),
+    }
+}
+
+/// Parse a single cell as a date in the given format.
+pub fn parse_date(
+    format: &DateFormat,
+    column: impl ToString,
+    value: &str,
+) -> Result<NaiveDate, ParseError> {
+    format.parse(value).map_err(|e| {
+        let kind = match e {
+            DateError::Format => ParseErrorKind::Date,
+            DateError::Invalid => ParseErrorKind::InvalidDate,
+        };
+        ParseError::new(kind, column, value)
+    })
+}
+
+/// Keep good rows. Bad rows fail in `Strict` mode and are collected in `Lenient` mode.
+pub(super) fn collect<T>(
+    mode: Mode,
+    results: impl Iterator<Item = Result<T, ParseError>>,
+) -> Result<(Vec<T>, Vec<ParseError>), ParseError> {
+    let mut good = vec![];
+    let mut skipped = vec![];
+    for r in results {
+        match r {
+            Ok(t) => good.push(t),
+            Err(e) if mode == Mode::Lenient => skipped.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((good, skipped))
+}
+
+/// SSI files use both ';' and ',', so guess from the header line.
+fn separator(data: &str) -> u8 {
+    let header = data.lines().next().unwrap_or("");
+    if header.contains(';') {
+        b';'
+    } else {
+        b','
+    }
+}
+
+fn column_index(headers: &csv::StringRecord, name: &str) -> Result<usize, ParseError> {
+    headers
+        .iter()
+        .position(|h| h.trim() == name)
+        .ok_or_else(|| ParseError::new(ParseErrorKind::MissingColumn, name, "").at_line(1))
+}
+
+fn cell<'a>(
+    record: &'a csv::StringRecord,
+    index: usize,
+    name: &str,
+) -> Result<&'a str, ParseError> {
+    record
+        .get(index)
+        .map(|c| c.trim())
+        .ok_or_else(|| ParseError::new(ParseErrorKind::Malformed, name, ""))
+}
+
+/// Whether a value of the row is one of `columns.missing`.
+fn is_missing(record: &csv::StringRecord, columns: &Columns, indices: &[usize]) -> bool {
+    indices[1..=columns.values.len()]
+        .iter()
+        .filter_map(|&i| record.get(i))
+        .any(|c| columns.missing.iter().any(|m| m == c.trim()))
+}
+
+fn read_row<V: Value>(
+    record: &csv::StringRecord,
+    columns: &Columns,
+    indices: &[usize],
+) -> Result<Row<V>, ParseError> {
+    let (value_indices, tag_indices) = indices[1..].split_at(columns.values.len());
+    let date = cell(record, indices[0], &columns.date)?;
+    Ok(Row {
+        date: parse_date(&columns.date_format, &columns.date, date)?,
+        value: columns
+            .values
+            .iter()
+            .zip(value_indices)
+            .map(|(name, &i)| {
+                let value = cell(record, i, name)?;
+                if columns.decimals {
+                    parse_decimal(name, value)
+                } else {
+                    parse_number(name, value)
+                }
+            })
+            .sum::<Result<_, _>>()?,
+        tags: columns
+            .tags
+            .iter()
+            .zip(tag_indices)
+            .map(|(name, &i)| cell(record, i, name).map(|c| c.to_string()))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Read the rows of a CSV file. A missing column in the header is an error in either mode.
+pub fn read_rows<V: Value>(
+    data: &str,
+    columns: &Columns,
+    mode: Mode,
+) -> Result<(Vec<Row<V>>, Vec<ParseError>), ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(separator(data))
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| ParseError::new(ParseErrorKind::Malformed, "", &e.to_string()).at_line(1))?
+        .clone();
+    let indices = std::iter::once(&columns.date)
+        .chain(&columns.values)
+        .chain(&columns.tags)
+        .map(|name| column_index(&headers, name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The csv crate miscounts lines with "\r\n" endings and places records after "\r" but
+    // before "\n", so count the newlines up to and including the record's first byte.
+    let line = |p: Option<&csv::Position>| {
+        p.map_or(0, |p| {
+            let end = (p.byte() as usize + 1).min(data.len());
+            data.as_bytes()[..end]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count() as u64
+                + 1
+        })
+    };
+    let rows = reader.records().filter_map(|r| match r {
+        Ok(record) if record.iter().all(|c| c.trim().is_empty()) => None,
+        Ok(record) if is_missing(&record, columns, &indices) => None,
+        Ok(record) => Some(
+            read_row(&record, columns, &indices).map_err(|e| e.at_line(line(record.position()))),
+        ),
+        Err(e) => Some(Err(ParseError::new(
+            ParseErrorKind::Malformed,
+            "",
+            &e.to_string(),
+        )
+        .at_line(line(e.position())))),
+    });
+
+    collect(mode, rows)
+}
+
+/// Dates that appear in more than one row with the same tag values, from the rows of
+/// `read_rows`.
+pub fn duplicates<V>(rows: &[Row<V>]) -> Vec<Duplicate> {
+    let mut counts: im::OrdMap<(Vec<String>, NaiveDate), usize> = im::OrdMap::new();
+    for row in rows {
+        *counts.entry((row.tags.clone(), row.date)).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, rows)| *rows > 1)
+        .map(|((tags, date), rows)| Duplicate {
+            file: None,
+            tags,
+            date,
+            rows,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(data: &str) -> Vec<(NaiveDate, i64, Vec<String>)> {
+        let columns = Columns::new("Dato", "Antal").tag("Regionsnavn");
+        read_rows(data, &columns, Mode::Strict)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|row| (row.date, row.value, row.tags))
+            .collect()
+    }
+
+    #[test]
+    fn quoted_fields_keep_the_separator() {
+        let data = "Regionsnavn;Dato;Antal\n\"Syd; Sønderjylland\";2021-01-04;3\n";
+        assert_eq!(
+            rows(data),
+            vec![(
+                NaiveDate::from_ymd(2021, 1, 4),
+                3,
+                vec!["Syd; Sønderjylland".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn columns_are_found_by_name_in_any_order() {
+        let data = "Dato,Regionsnavn,Antal\n2021-01-04,Nord,3\n2021-01-05,Syd,4\n";
+        let reordered = "Antal,Dato,Andet,Regionsnavn\n3,2021-01-04,x,Nord\n4,2021-01-05,y,Syd\n";
+        assert_eq!(rows(data), rows(reordered));
+        assert_eq!(rows(data)[1].2, vec!["Syd".to_string()]);
+    }
+
+    const BAD: &str =
+        "Dato;Regionsnavn;Antal\n2021-01-04;Nord;3\n2021-01-05;Syd;tre\n2021-01-06;Syd;5\n";
+
+    #[test]
+    fn errors_say_where() {
+        let columns = Columns::new("Dato", "Antal").tag("Regionsnavn");
+        let error = read_rows::<i64>(BAD, &columns, Mode::Strict).err().unwrap();
+        assert_eq!(error.kind, ParseErrorKind::Number);
+        assert_eq!((error.line, error.column.as_str()), (3, "Antal"));
+        assert_eq!(
+            error.in_file("a.csv").to_string(),
+            "a.csv:3: invalid number in column \"Antal\": \"tre\""
+        );
+
+        let error = read_rows::<i64>(BAD, &Columns::new("Dato", "Antal i alt"), Mode::Strict)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::MissingColumn);
+    }
+
+    #[test]
+    fn lenient_mode_skips_bad_rows() {
+        let columns = Columns::new("Dato", "Antal").tag("Regionsnavn");
+        let (rows, skipped) = read_rows::<i64>(BAD, &columns, Mode::Lenient).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].line, 3);
+    }
+
+    const DATA: &str = "date;location;value\n2021-06-01;DNK;12.5\n2021-06-01;SWE;3.0\n";
+
+    fn columns() -> Columns {
+        Columns::new("date", "value").tag("location")
+    }
+
+    #[test]
+    fn fractions() {
+        let (rows, _): (Vec<Row<f64>>, _) = read_rows(DATA, &columns(), Mode::Strict).unwrap();
+        let values: Vec<f64> = rows.iter().map(|r| r.value).collect();
+        assert_eq!(values, vec![12.5, 3.0]);
+        assert_eq!(rows[0].tags, vec!["DNK".to_string()]);
+    }
+
+    #[test]
+    fn whole_numbers() {
+        let error = read_rows::<i64>(DATA, &columns(), Mode::Strict)
+            .err()
+            .unwrap();
+        assert_eq!((error.kind, error.line), (ParseErrorKind::Number, 2));
+
+        let (rows, skipped) = read_rows::<i64>(DATA, &columns(), Mode::Lenient).unwrap();
+        assert_eq!(rows.iter().map(|r| r.value).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(skipped.len(), 1);
+
+        let (rows, _) = read_rows::<i64>(DATA, &columns().decimals(), Mode::Strict).unwrap();
+        assert_eq!(
+            rows.iter().map(|r| r.value).collect::<Vec<_>>(),
+            vec![13, 3]
+        );
+    }
+
+    #[test]
+    fn duplicate_dates() {
+        let data = "date;value\n2021-06-01;1\n2021-06-01;2\n2021-06-02;3\n";
+        let (rows, _) =
+            read_rows::<i64>(data, &Columns::new("date", "value"), Mode::Strict).unwrap();
+        let duplicates = duplicates(&rows);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].date, NaiveDate::from_ymd(2021, 6, 1));
+        assert_eq!(duplicates[0].rows, 2);
+    }
+}
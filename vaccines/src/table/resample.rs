@@ -0,0 +1,188 @@
+use super::{aggregate, TimeSeries, TimeSeriesGroup, Value, Values};
+use chrono::{Datelike, Duration, NaiveDate};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+];
+
+/// How long each date of a series is. A week or month is kept as its first day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    /// ISO weeks, from Monday to Sunday, like SSI's "uge 5".
+    Week,
+    Month,
+}
+
+impl Period {
+    /// The first day of the period `date` is in.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// The last day of the period `date` is in.
+    pub fn end(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => self.start(date) + Duration::days(6),
+            Period::Month => {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                NaiveDate::from_ymd(year, month, 1) - Duration::days(1)
+            }
+        }
+    }
+
+    /// How the period starting on `date` is shown in charts: "2021-02-05", "uge 5 2021" or
+    /// "feb 2021".
+    pub fn label(self, date: &NaiveDate) -> String {
+        match self {
+            Period::Day => date.format("%Y-%m-%d").to_string(),
+            Period::Week => {
+                let week = date.iso_week();
+                format!("uge {} {}", week.week(), week.year())
+            }
+            Period::Month => format!("{} {}", MONTHS[date.month0() as usize], date.year()),
+        }
+    }
+}
+
+impl<V: Value> TimeSeries<V> {
+    /// One value per period, combined from the filled values by the aggregation of the series.
+    /// The first period may only have some of its days. The last period is left out until the
+    /// series reaches its last day, so a week just begun does not look like a drop. A weekly
+    /// series dated by Monday reaches the Sunday after its last date.
+    pub fn resample(&self, period: Period) -> Self {
+        let filled = self.clone().filled();
+        let mut values = Values::new();
+        for (date, value) in &filled.data {
+            values.entry(period.start(*date)).or_default().push(*value);
+        }
+        if let Some(last) = filled.latest_date() {
+            let reached = *last + Duration::days(self.step().unwrap_or(1) - 1);
+            if reached < period.end(*last) {
+                values.remove(&period.start(*last));
+            }
+        }
+        TimeSeries {
+            data: aggregate(values, self.aggregation),
+            ..self.clone()
+        }
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// Every series, and the overlay, by week or month, see `TimeSeries::resample`. Charts are
+    /// labelled by the period.
+    pub fn resample(self, period: Period) -> Self {
+        let resample = |all: Vec<TimeSeries<V>>| all.iter().map(|ts| ts.resample(period)).collect();
+        TimeSeriesGroup {
+            series: resample(self.series),
+            overlay: resample(self.overlay),
+            period,
+            ..self
+        }
+    }
+
+    pub fn period(&self) -> Period {
+        self.period
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{Aggregation, Fill};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// A value of 1 every day from `from` through `to`.
+    fn daily(from: &str, to: &str) -> TimeSeries {
+        let (from, to) = (date(from), date(to));
+        let days = (to - from).num_days();
+        let data = (0..=days).map(|d| (from + Duration::days(d), 1)).collect();
+        TimeSeries::new(im::OrdSet::unit("a".to_string()), data).with_fill(Fill::Zero)
+    }
+
+    #[test]
+    fn weeks_across_new_year() {
+        // 2020 has an ISO week 53, from Monday 28 December to Sunday 3 January.
+        let weeks = daily("2020-12-21", "2021-01-10").resample(Period::Week);
+        let expected: im::OrdMap<NaiveDate, i64> = vec![
+            (date("2020-12-21"), 7),
+            (date("2020-12-28"), 7),
+            (date("2021-01-04"), 7),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(weeks.data, expected);
+        assert_eq!(Period::Week.label(&date("2020-12-28")), "uge 53 2020");
+    }
+
+    #[test]
+    fn last_period_only_once_complete() {
+        // Wednesday: the last week has only three days.
+        let weeks = daily("2021-01-04", "2021-01-13").resample(Period::Week);
+        assert_eq!(
+            weeks.data.keys().cloned().collect::<Vec<_>>(),
+            vec![date("2021-01-04")]
+        );
+
+        let months = daily("2021-01-15", "2021-02-28").resample(Period::Month);
+        let expected: im::OrdMap<NaiveDate, i64> =
+            vec![(date("2021-01-01"), 17), (date("2021-02-01"), 28)]
+                .into_iter()
+                .collect();
+        assert_eq!(months.data, expected);
+        assert_eq!(Period::Month.end(date("2020-02-10")), date("2020-02-29"));
+        assert_eq!(Period::Month.end(date("2020-12-10")), date("2020-12-31"));
+    }
+
+    #[test]
+    fn weekly_series_reach_the_end_of_their_week() {
+        let data = vec![(date("2021-01-04"), 10), (date("2021-01-11"), 20)];
+        let weekly: TimeSeries = TimeSeries::new(
+            im::OrdSet::unit("a".to_string()),
+            data.into_iter().collect(),
+        );
+        assert_eq!(weekly.resample(Period::Week).data.len(), 2);
+    }
+
+    #[test]
+    fn aggregation_of_the_series() {
+        let data = (0..7)
+            .map(|d| (date("2021-01-04") + Duration::days(d), d + 1))
+            .collect();
+        let ts = TimeSeries::new(im::OrdSet::unit("a".to_string()), data);
+        let week = |aggregation| {
+            let ts = ts
+                .clone()
+                .with_aggregation(aggregation)
+                .resample(Period::Week);
+            ts.data.get(&date("2021-01-04")).cloned()
+        };
+        assert_eq!(week(Aggregation::Sum), Some(28));
+        assert_eq!(week(Aggregation::Mean), Some(4));
+        assert_eq!(week(Aggregation::Last), Some(7));
+        assert_eq!(week(Aggregation::Min), Some(1));
+        assert_eq!(week(Aggregation::Max), Some(7));
+    }
+
+    #[test]
+    fn loaded_series_keep_the_aggregation_of_the_file() {
+        use crate::table::Columns;
+        let data = "Dato;Antal\n2021-01-04;2\n2021-01-04;4\n2021-01-05;6\n";
+        let columns = Columns::new("Dato", "Antal").aggregate(Aggregation::Mean);
+        let ts: TimeSeries = TimeSeries::from_csv(im::OrdSet::new(), data, &columns).unwrap();
+        assert_eq!(ts.aggregation, Aggregation::Mean);
+        assert_eq!(ts.data.get(&date("2021-01-04")), Some(&3));
+    }
+}
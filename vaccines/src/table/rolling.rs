@@ -0,0 +1,226 @@
+use super::{TimeSeries, TimeSeriesGroup, Value};
+use chrono::Duration;
+
+/// What to compute over the values in each window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Statistic {
+    Mean,
+    Sum,
+    Median,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    /// The window ends on the date, so the value only depends on the days before it.
+    Trailing,
+    /// The date is in the middle of the window. An even window has one more value before the
+    /// date than after it.
+    Centred,
+}
+
+/// A rolling window of `size` values at the step of the series, e.g. 7 for a week of daily
+/// data. Dates without a value after filling are left out of the window.
+#[derive(Clone, Copy, Debug)]
+pub struct Window {
+    pub statistic: Statistic,
+    pub size: usize,
+    pub align: Align,
+    /// Windows with fewer values, like at the start of a series, give no value.
+    pub min_values: usize,
+}
+
+impl Window {
+    pub fn new(statistic: Statistic, size: usize) -> Self {
+        Window {
+            statistic,
+            size,
+            align: Align::Trailing,
+            min_values: size,
+        }
+    }
+
+    pub fn mean(size: usize) -> Self {
+        Self::new(Statistic::Mean, size)
+    }
+
+    pub fn sum(size: usize) -> Self {
+        Self::new(Statistic::Sum, size)
+    }
+
+    pub fn median(size: usize) -> Self {
+        Self::new(Statistic::Median, size)
+    }
+
+    pub fn min(size: usize) -> Self {
+        Self::new(Statistic::Min, size)
+    }
+
+    pub fn max(size: usize) -> Self {
+        Self::new(Statistic::Max, size)
+    }
+
+    pub fn centred(self) -> Self {
+        Window {
+            align: Align::Centred,
+            ..self
+        }
+    }
+
+    pub fn min_values(self, min_values: usize) -> Self {
+        Window { min_values, ..self }
+    }
+
+    /// Offsets in steps from the date to the values in its window.
+    fn offsets(&self) -> std::ops::RangeInclusive<i64> {
+        let size = self.size as i64;
+        match self.align {
+            Align::Trailing => -(size - 1)..=0,
+            Align::Centred => -(size / 2)..=(size - 1) / 2,
+        }
+    }
+
+    fn apply<V: Value>(&self, mut values: Vec<V>) -> V {
+        let mean = |values: &[V]| {
+            V::from_f64(values.iter().map(|v| v.to_f64()).sum::<f64>() / values.len() as f64)
+        };
+        let order = |a: &V, b: &V| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal);
+        match self.statistic {
+            Statistic::Mean => mean(&values),
+            Statistic::Sum => values.into_iter().sum(),
+            Statistic::Median => {
+                values.sort_by(order);
+                let middle = values.len() / 2;
+                if values.len() % 2 == 1 {
+                    values[middle]
+                } else {
+                    mean(&values[middle - 1..=middle])
+                }
+            }
+            Statistic::Min => values.into_iter().min_by(order).unwrap(),
+            Statistic::Max => values.into_iter().max_by(order).unwrap(),
+        }
+    }
+}
+
+impl<V: Value> TimeSeries<V> {
+    /// The statistic of the window around each date of the series, e.g. `Window::mean(7)` for a
+    /// 7-day average. Mean and median are rounded for whole numbers.
+    pub fn rolling(&self, window: &Window) -> Self {
+        let step = Duration::days(self.step().unwrap_or(1));
+        let data = self
+            .data
+            .keys()
+            .filter_map(|date| {
+                let values: Vec<V> = window
+                    .offsets()
+                    .filter_map(|offset| self.value(&(*date + step * offset as i32)))
+                    .collect();
+                if values.is_empty() || values.len() < window.min_values {
+                    return None;
+                }
+                Some((*date, window.apply(values)))
+            })
+            .collect();
+        TimeSeries {
+            data,
+            ..self.clone()
+        }
+    }
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    pub fn rolling(self, window: &Window) -> Self {
+        TimeSeriesGroup {
+            series: self.series.iter().map(|ts| ts.rolling(window)).collect(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Fill;
+    use chrono::NaiveDate;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    /// 1, 2, ... on the days from 2021-01-01.
+    fn days(values: &[i64]) -> TimeSeries {
+        let start = date("2021-01-01");
+        let data = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + Duration::days(i as i64), *v))
+            .collect();
+        TimeSeries::new(im::OrdSet::new(), data)
+    }
+
+    fn values<V: Value>(ts: &TimeSeries<V>) -> Vec<V> {
+        ts.data.values().cloned().collect()
+    }
+
+    #[test]
+    fn trailing_windows_start_when_full() {
+        let ts = days(&[1, 2, 3, 4, 5]);
+        assert_eq!(values(&ts.rolling(&Window::sum(3))), vec![6, 9, 12]);
+        assert_eq!(
+            ts.rolling(&Window::sum(3)).data.keys().next(),
+            Some(&date("2021-01-03"))
+        );
+        let partial = Window::sum(3).min_values(1);
+        assert_eq!(values(&ts.rolling(&partial)), vec![1, 3, 6, 9, 12]);
+    }
+
+    #[test]
+    fn centred_windows() {
+        let ts = days(&[1, 2, 3, 4, 5]);
+        let mean = ts.rolling(&Window::mean(3).centred());
+        assert_eq!(mean.data.keys().next(), Some(&date("2021-01-02")));
+        assert_eq!(values(&mean), vec![2, 3, 4]);
+        // One more value before the date than after it.
+        let even = ts.rolling(&Window::max(4).centred());
+        assert_eq!(even.data.keys().next(), Some(&date("2021-01-03")));
+        assert_eq!(values(&even), vec![4, 5]);
+    }
+
+    #[test]
+    fn statistics() {
+        let ts = days(&[4, 1, 3, 2]).map(|v| v as f64);
+        assert_eq!(values(&ts.rolling(&Window::mean(4))), vec![2.5]);
+        assert_eq!(values(&ts.rolling(&Window::median(4))), vec![2.5]);
+        assert_eq!(values(&ts.rolling(&Window::median(3))), vec![3.0, 2.0]);
+        assert_eq!(values(&ts.rolling(&Window::min(4))), vec![1.0]);
+        // Whole numbers are rounded.
+        assert_eq!(values(&days(&[1, 2]).rolling(&Window::mean(2))), vec![2]);
+    }
+
+    #[test]
+    fn missing_dates_by_the_fill_policy() {
+        let mut gap = days(&[2, 2, 2, 2]);
+        gap.data.remove(&date("2021-01-02"));
+        assert!(gap.rolling(&Window::sum(3)).data.is_empty());
+        let partial = Window::sum(3).min_values(2);
+        assert_eq!(values(&gap.rolling(&partial)), vec![4, 4]);
+        let zero = gap.with_fill(Fill::Zero);
+        // Zero also fills the days before the series starts.
+        assert_eq!(values(&zero.rolling(&Window::sum(3))), vec![2, 4, 4]);
+    }
+
+    #[test]
+    fn weekly_windows_are_in_weeks() {
+        let data = vec![
+            (date("2021-01-04"), 7),
+            (date("2021-01-11"), 14),
+            (date("2021-01-18"), 21),
+        ]
+        .into_iter()
+        .collect();
+        let weekly: TimeSeries = TimeSeries::new(im::OrdSet::new(), data);
+        assert_eq!(values(&weekly.rolling(&Window::mean(2))), vec![11, 18]);
+    }
+}
@@ -0,0 +1,118 @@
+use super::reader::{Columns, Mode, ParseError, ParseErrorKind};
+use super::{TimeSeriesGroup, Value};
+
+const TIME_PERIOD: &str = "TIME_PERIOD";
+const OBS_VALUE: &str = "OBS_VALUE";
+/// Columns about the dataflow rather than a dimension of the observation. Eurostat adds
+/// "LAST UPDATE" after the dataflow.
+const STRUCTURE: [&str; 5] = [
+    "DATAFLOW",
+    "STRUCTURE",
+    "STRUCTURE_ID",
+    "ACTION",
+    "LAST UPDATE",
+];
+
+/// The id of a header, e.g. "GEO" for "GEO: Geopolitical entity" in files exported with labels.
+fn column_id(header: &str) -> &str {
+    header.split(':').next().unwrap_or("").trim()
+}
+
+impl<V: Value> TimeSeriesGroup<V> {
+    /// Read an SDMX-CSV file, e.g. from Eurostat or ECDC, with one series per combination of
+    /// dimension values. The dimensions are the columns before OBS_VALUE, except TIME_PERIOD
+    /// which holds the dates. Attributes after it, like OBS_FLAG, are ignored and empty
+    /// observations are left out.
+    pub fn from_sdmx_csv(tags: im::OrdSet<String>, data: &str) -> Result<Self, ParseError> {
+        Ok(Self::from_sdmx_csv_mode(tags, data, Mode::Strict)?.0)
+    }
+
+    /// `from_sdmx_csv` in the given mode, also returning the rows skipped in `Mode::Lenient`.
+    pub fn from_sdmx_csv_mode(
+        tags: im::OrdSet<String>,
+        data: &str,
+        mode: Mode,
+    ) -> Result<(Self, Vec<ParseError>), ParseError> {
+        let header = data.lines().next().unwrap_or("");
+        let separator = if header.contains(';') { ';' } else { ',' };
+        let headers: Vec<&str> = header
+            .split(separator)
+            .map(|h| h.trim_start_matches('\u{feff}').trim().trim_matches('"'))
+            .collect();
+        let find = |id: &str| {
+            headers
+                .iter()
+                .find(|h| column_id(h) == id)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::MissingColumn, id, "").at_line(1))
+        };
+
+        let columns = headers
+            .iter()
+            .take_while(|h| column_id(h) != OBS_VALUE)
+            .filter(|h| column_id(h) != TIME_PERIOD && !STRUCTURE.contains(&column_id(h)))
+            .fold(
+                Columns::new(find(TIME_PERIOD)?, find(OBS_VALUE)?),
+                |c, h| c.tag(h),
+            )
+            .missing("");
+        Self::from_csv_mode(tags, data, &columns, mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const DATA: &str = "\
+DATAFLOW,LAST UPDATE,freq,geo,TIME_PERIOD,OBS_VALUE,OBS_FLAG
+ESTAT:X(1.0),01/07/21,D,DK,2021-06-01,12,
+ESTAT:X(1.0),01/07/21,D,DK,2021-06-02,,p
+ESTAT:X(1.0),01/07/21,D,SE,2021-06-01,7,
+";
+
+    fn tags() -> im::OrdSet<String> {
+        im::OrdSet::unit("Test".to_string())
+    }
+
+    #[test]
+    fn dimensions_become_tags() {
+        let group: TimeSeriesGroup = TimeSeriesGroup::from_sdmx_csv(tags(), DATA).unwrap();
+        let series = group.series();
+        assert_eq!(series.len(), 2);
+
+        let dk = series.iter().find(|ts| ts.tags.contains("DK")).unwrap();
+        let expected: im::OrdSet<String> =
+            vec!["Test".to_string(), "D".to_string(), "DK".to_string()].into();
+        assert_eq!(dk.tags, expected);
+        assert_eq!(dk.data.len(), 1);
+        assert_eq!(dk.data.get(&NaiveDate::from_ymd(2021, 6, 1)), Some(&12));
+    }
+
+    #[test]
+    fn fractions_are_not_whole_numbers() {
+        let data = DATA.replace(",12,", ",12.5,");
+        let error = TimeSeriesGroup::<i64>::from_sdmx_csv(tags(), &data)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::Number);
+        assert_eq!(error.line, 2);
+
+        let group: TimeSeriesGroup<f64> = TimeSeriesGroup::from_sdmx_csv(tags(), &data).unwrap();
+        let dk = group
+            .series()
+            .iter()
+            .find(|ts| ts.tags.contains("DK"))
+            .unwrap();
+        assert_eq!(dk.data.get(&NaiveDate::from_ymd(2021, 6, 1)), Some(&12.5));
+    }
+
+    #[test]
+    fn labelled_headers() {
+        let data = "freq: Time frequency;TIME_PERIOD: Time;OBS_VALUE: Observation value\n\
+                    D;2021-06-01;3\n";
+        let group: TimeSeriesGroup = TimeSeriesGroup::from_sdmx_csv(tags(), data).unwrap();
+        assert_eq!(group.series()[0].data.values().sum::<i64>(), 3);
+        assert!(group.series()[0].tags.contains("D"));
+    }
+}
@@ -0,0 +1,347 @@
+use super::reader::Duplicate;
+use super::{TimeSeries, TimeSeriesGroup, Value};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::fmt;
+
+/// Thresholds for the checks in `Report`.
+#[derive(Clone, Debug)]
+pub struct Rules {
+    /// Flag a value that is this many times larger or smaller than the one a week before...
+    pub jump_factor: f64,
+    /// ...and differs from it by at least this much, so small counts can vary freely.
+    pub jump_min: i64,
+    /// Only look for jumps in this many days at the end of a series. Early 2020 is all jumps.
+    pub recent: i64,
+    /// Flag series whose latest date is more than this many steps before `today`, e.g. 3 days
+    /// for a daily series or 3 weeks for a weekly one.
+    pub max_age: i64,
+    /// The day the age of the data is measured from: the date given with `--as-of`, or else
+    /// the day the page is made.
+    pub today: NaiveDate,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Issue {
+    /// Dates missing between `after` and `before`, judged by the usual step of the series, so
+    /// weekly series are not flagged for the days between weeks. Dates filled by the fill
+    /// policy of the series, like the days without cases left out of the SSI files, are not
+    /// missing.
+    MissingDates {
+        after: NaiveDate,
+        before: NaiveDate,
+        missing: i64,
+    },
+    DuplicateDate {
+        date: NaiveDate,
+        rows: usize,
+    },
+    Negative {
+        date: NaiveDate,
+        value: f64,
+    },
+    /// `previous` is the value a week before `date`, or the one before that if it is missing.
+    Jump {
+        date: NaiveDate,
+        previous: f64,
+        value: f64,
+    },
+    Stale {
+        latest: NaiveDate,
+        days: i64,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    pub dataset: String,
+    /// The tags of the series, e.g. "Hovedstaden,Smittede per dag".
+    pub series: String,
+    #[serde(flatten)]
+    pub issue: Issue,
+}
+
+/// What the checks found in the loaded data, written as JSON with `to_json` and shown on the
+/// page as a warning.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+}
+
+impl Rules {
+    pub fn new(today: NaiveDate) -> Self {
+        Rules {
+            jump_factor: 5.0,
+            jump_min: 100,
+            recent: 8 * 7,
+            max_age: 3,
+            today,
+        }
+    }
+
+    pub fn check<V: Value>(&self, ts: &TimeSeries<V>) -> Vec<Issue> {
+        let mut issues = vec![];
+        let latest = match ts.latest_date() {
+            Some(latest) => *latest,
+            None => return issues,
+        };
+
+        let filled = ts.clone().filled();
+        if let Some(step) = ts.step() {
+            let dates: Vec<&NaiveDate> = filled.data.keys().collect();
+            for pair in dates.windows(2) {
+                let gap = (*pair[1] - *pair[0]).num_days();
+                if gap > step {
+                    issues.push(Issue::MissingDates {
+                        after: *pair[0],
+                        before: *pair[1],
+                        missing: gap / step - 1,
+                    });
+                }
+            }
+        }
+
+        for (date, value) in &ts.data {
+            if *value < V::default() {
+                issues.push(Issue::Negative {
+                    date: *date,
+                    value: value.to_f64(),
+                });
+            }
+        }
+
+        for (&date, &value) in &ts.data {
+            // Compare with a week before rather than the day before, so the low numbers every
+            // weekend are not jumps.
+            let previous = match ts.data.get_prev(&(date - Duration::days(7))) {
+                Some((_, previous)) => previous.to_f64(),
+                None => continue,
+            };
+            let value = value.to_f64();
+            let (small, large) = (previous.min(value).max(0.0), previous.max(value));
+            let recent = (latest - date).num_days() < self.recent;
+            if recent
+                && large - small >= self.jump_min as f64
+                && large > small * self.jump_factor
+            {
+                issues.push(Issue::Jump {
+                    date,
+                    previous,
+                    value,
+                });
+            }
+        }
+
+        let days = (self.today - latest).num_days();
+        if days > self.max_age * ts.step().unwrap_or(1) {
+            issues.push(Issue::Stale { latest, days });
+        }
+        issues
+    }
+}
+
+fn series_name(tags: &im::OrdSet<String>) -> String {
+    tags.iter().cloned().collect::<Vec<_>>().join(",")
+}
+
+impl Report {
+    pub fn check_series<V: Value>(&mut self, dataset: &str, ts: &TimeSeries<V>, rules: &Rules) {
+        for issue in rules.check(ts) {
+            self.findings.push(Finding {
+                dataset: dataset.to_string(),
+                series: series_name(&ts.tags),
+                issue,
+            });
+        }
+    }
+
+    pub fn check<V: Value>(&mut self, dataset: &str, group: &TimeSeriesGroup<V>, rules: &Rules) {
+        for ts in group.series() {
+            self.check_series(dataset, ts, rules);
+        }
+    }
+
+    /// Add the duplicates found while loading, see `DataDir::duplicates`.
+    pub fn duplicates(&mut self, duplicates: &[Duplicate]) {
+        for d in duplicates {
+            self.findings.push(Finding {
+                dataset: d.file.clone().unwrap_or_default(),
+                series: d.tags.join(","),
+                issue: Issue::DuplicateDate {
+                    date: d.date,
+                    rows: d.rows,
+                },
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// In Danish, for the banner on the page.
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::MissingDates {
+                after,
+                before,
+                missing,
+            } => write!(
+                f,
+                "mangler {} datoer mellem {} og {}",
+                missing, after, before
+            ),
+            Issue::DuplicateDate { date, rows } => write!(f, "{} står {} gange", date, rows),
+            Issue::Negative { date, value } => write!(f, "negativt tal {} den {}", value, date),
+            Issue::Jump {
+                date,
+                previous,
+                value,
+            } => write!(
+                f,
+                "spring fra {} ugen før til {} den {}",
+                previous, value, date
+            ),
+            Issue::Stale { latest, days } => {
+                write!(f, "seneste data er fra {}, {} dage gamle", latest, days)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.dataset, self.series, self.issue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Fill;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn series(values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit("a".to_string()), data)
+    }
+
+    fn rules() -> Rules {
+        Rules::new(date("2021-01-10"))
+    }
+
+    fn missing(issues: &[Issue]) -> Vec<&Issue> {
+        issues
+            .iter()
+            .filter(|i| matches!(i, Issue::MissingDates { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn gaps_are_missing_dates() {
+        let ts = series(&[
+            ("2021-01-01", 1),
+            ("2021-01-02", 1),
+            ("2021-01-05", 1),
+            ("2021-01-06", 1),
+        ]);
+        assert_eq!(
+            missing(&rules().check(&ts)),
+            vec![&Issue::MissingDates {
+                after: date("2021-01-02"),
+                before: date("2021-01-05"),
+                missing: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn filled_dates_are_not_missing() {
+        let values = [
+            ("2021-01-01", 1),
+            ("2021-01-02", 1),
+            ("2021-01-05", 1),
+            ("2021-01-06", 1),
+        ];
+        for fill in &[Fill::Zero, Fill::Forward, Fill::Interpolate] {
+            assert!(missing(&rules().check(&series(&values).with_fill(*fill))).is_empty());
+        }
+    }
+
+    fn stale(issues: &[Issue]) -> bool {
+        issues.iter().any(|i| matches!(i, Issue::Stale { .. }))
+    }
+
+    #[test]
+    fn age_is_counted_in_steps() {
+        let weekly = series(&[("2020-12-14", 1), ("2020-12-21", 1), ("2020-12-28", 1)]);
+        assert!(!stale(&Rules::new(date("2021-01-10")).check(&weekly)));
+        assert!(stale(&Rules::new(date("2021-01-19")).check(&weekly)));
+
+        let daily = series(&[("2021-01-05", 1), ("2021-01-06", 1), ("2021-01-07", 1)]);
+        assert!(!stale(&Rules::new(date("2021-01-10")).check(&daily)));
+        assert!(stale(&Rules::new(date("2021-01-11")).check(&daily)));
+    }
+
+    #[test]
+    fn negative_values() {
+        let ts = series(&[("2021-01-08", 3), ("2021-01-09", -2), ("2021-01-10", 0)]);
+        let negative: Vec<Issue> = rules()
+            .check(&ts)
+            .into_iter()
+            .filter(|i| matches!(i, Issue::Negative { .. }))
+            .collect();
+        assert_eq!(
+            negative,
+            vec![Issue::Negative {
+                date: date("2021-01-09"),
+                value: -2.0,
+            }]
+        );
+    }
+
+    fn jumps(ts: &TimeSeries) -> Vec<Issue> {
+        rules()
+            .check(ts)
+            .into_iter()
+            .filter(|i| matches!(i, Issue::Jump { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn jumps_from_a_week_before() {
+        let ts = series(&[
+            ("2020-12-25", 50),
+            ("2021-01-01", 100),
+            ("2021-01-08", 1000),
+            ("2021-01-09", 10),
+        ]);
+        assert_eq!(
+            jumps(&ts),
+            vec![Issue::Jump {
+                date: date("2021-01-08"),
+                previous: 100.0,
+                value: 1000.0,
+            }]
+        );
+
+        // Small counts vary freely, and so do the first weeks of a long series.
+        assert!(jumps(&series(&[("2021-01-01", 1), ("2021-01-08", 90)])).is_empty());
+        let old = series(&[
+            ("2020-01-01", 100),
+            ("2020-01-08", 1000),
+            ("2021-01-08", 1000),
+        ]);
+        assert!(jumps(&old).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::Number;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// What a `TimeSeries` can hold: whole numbers, like the counts in the SSI files, or
+/// fractions, like incidence per 100,000, test positivity, coverage or Rt.
+pub trait Value:
+    Copy
+    + Default
+    + PartialEq
+    + PartialOrd
+    + fmt::Debug
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Sum
+{
+    /// Decimals shown in charts, unless set with `TimeSeriesGroup::with_decimals`.
+    const DECIMALS: usize;
+
+    /// Rounded to the nearest whole number for `i64`.
+    fn from_f64(value: f64) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    /// Parse a value as written in a data file, like "1234", or "12.5" for fractions. Whole
+    /// numbers are also accepted as "1234.0", but "12.5" is not one.
+    fn parse(s: &str) -> Option<Self>;
+
+    /// `self * num / den`. Whole numbers are divided last and rounded down, like the goals
+    /// have always been drawn.
+    fn scale(self, num: i64, den: i64) -> Self;
+
+    /// The value for a chart, rounded to `decimals`. Values JSON cannot hold, like NaN from
+    /// dividing by zero, become gaps.
+    fn to_json(self, decimals: usize) -> Option<Number>;
+}
+
+impl Value for i64 {
+    const DECIMALS: usize = 0;
+
+    fn from_f64(value: f64) -> Self {
+        value.round() as i64
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        s.parse().ok().or_else(|| {
+            s.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && v.fract() == 0.0)
+                .map(|v| v as i64)
+        })
+    }
+
+    fn scale(self, num: i64, den: i64) -> Self {
+        self * num / den
+    }
+
+    fn to_json(self, _decimals: usize) -> Option<Number> {
+        Some(self.into())
+    }
+}
+
+impl Value for f64 {
+    const DECIMALS: usize = 2;
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        s.parse().ok().filter(|v: &f64| v.is_finite())
+    }
+
+    fn scale(self, num: i64, den: i64) -> Self {
+        self * num as f64 / den as f64
+    }
+
+    fn to_json(self, decimals: usize) -> Option<Number> {
+        let factor = 10f64.powi(decimals as i32);
+        Number::from_f64((self * factor).round() / factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_numbers_with_a_decimal_point() {
+        assert_eq!(i64::parse("1234"), Some(1234));
+        assert_eq!(i64::parse("1234.0"), Some(1234));
+        assert_eq!(i64::parse("12.5"), None);
+        assert_eq!(i64::parse("tre"), None);
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(f64::parse("12.5"), Some(12.5));
+        assert_eq!(f64::parse("3"), Some(3.0));
+        assert_eq!(f64::parse("NaN"), None);
+        assert_eq!(f64::parse("inf"), None);
+    }
+
+    #[test]
+    fn chart_values() {
+        assert_eq!(1234i64.to_json(2), Some(1234.into()));
+        assert_eq!(12.345f64.to_json(1), Number::from_f64(12.3));
+        assert_eq!(f64::NAN.to_json(2), None);
+        assert_eq!(f64::INFINITY.to_json(2), None);
+    }
+}
@@ -0,0 +1,159 @@
+use super::TimeSeries;
+use chrono::NaiveDate;
+use std::fmt;
+
+/// Every published version of a series, keyed by publication date. SSI revises recent days
+/// as late registrations come in, so the same date can have a different value in each release.
+#[derive(Clone, Default)]
+pub struct Vintages {
+    pub releases: im::OrdMap<NaiveDate, TimeSeries>,
+}
+
+/// How the value for each date developed over the releases, keyed by the number of days from
+/// the date to the publication. Delay 0 is the release published on the day itself.
+#[derive(Clone, Default)]
+pub struct Triangle {
+    pub rows: im::OrdMap<NaiveDate, im::OrdMap<i64, i64>>,
+}
+
+impl Vintages {
+    pub fn new(releases: im::OrdMap<NaiveDate, TimeSeries>) -> Self {
+        Vintages { releases }
+    }
+
+    pub fn insert(&mut self, published: NaiveDate, ts: TimeSeries) {
+        self.releases.insert(published, ts);
+    }
+
+    /// The series as known on `published`, i.e. the newest release on or before that date.
+    pub fn as_of(&self, published: NaiveDate) -> Option<&TimeSeries> {
+        self.releases.get_prev(&published).map(|(_, ts)| ts)
+    }
+
+    /// The value for `date` as known on `published`.
+    pub fn value(&self, date: NaiveDate, published: NaiveDate) -> Option<i64> {
+        self.as_of(published)?.data.get(&date).cloned()
+    }
+
+    /// The revision triangle up to `max_delay` days after each date. Dates a release does not
+    /// have, like the days after it, are left out, so recent dates have short rows.
+    pub fn triangle(&self, max_delay: i64) -> Triangle {
+        let mut rows: im::OrdMap<NaiveDate, im::OrdMap<i64, i64>> = im::OrdMap::new();
+        for (published, ts) in &self.releases {
+            for (date, value) in &ts.data {
+                let delay = (*published - *date).num_days();
+                if (0..=max_delay).contains(&delay) {
+                    rows.entry(*date).or_default().insert(delay, *value);
+                }
+            }
+        }
+        Triangle { rows }
+    }
+}
+
+impl Triangle {
+    pub fn max_delay(&self) -> Option<i64> {
+        self.rows
+            .values()
+            .filter_map(|r| r.keys().next_back())
+            .max()
+            .cloned()
+    }
+}
+
+/// Semicolon separated like the SSI files, a row per date and a column per delay in days.
+impl fmt::Display for Triangle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max_delay = self.max_delay().unwrap_or(0);
+        write!(f, "Dato")?;
+        for delay in 0..=max_delay {
+            write!(f, ";{}", delay)?;
+        }
+        writeln!(f)?;
+        for (date, row) in &self.rows {
+            write!(f, "{}", date)?;
+            for delay in 0..=max_delay {
+                match row.get(&delay) {
+                    Some(value) => write!(f, ";{}", value)?,
+                    None => write!(f, ";")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn release(values: &[(&str, i64)]) -> TimeSeries {
+        let data = values.iter().map(|(d, v)| (date(d), *v)).collect();
+        TimeSeries::new(im::OrdSet::unit("Indlagte".to_string()), data)
+    }
+
+    /// Admissions on 1 and 2 June as first published and as revised over the next days.
+    fn vintages() -> Vintages {
+        let mut vintages = Vintages::default();
+        vintages.insert(date("2021-06-01"), release(&[("2021-06-01", 5)]));
+        vintages.insert(
+            date("2021-06-02"),
+            release(&[("2021-06-01", 8), ("2021-06-02", 4)]),
+        );
+        vintages.insert(
+            date("2021-06-04"),
+            release(&[("2021-06-01", 9), ("2021-06-02", 7)]),
+        );
+        vintages
+    }
+
+    #[test]
+    fn as_of_is_the_newest_release_up_to_the_date() {
+        let vintages = vintages();
+        let published = |d| vintages.as_of(date(d)).map(|ts| ts.data.len());
+        assert_eq!(published("2021-05-31"), None);
+        assert_eq!(published("2021-06-01"), Some(1));
+        assert_eq!(published("2021-06-03"), Some(2));
+        assert_eq!(published("2021-06-30"), Some(2));
+    }
+
+    #[test]
+    fn value_as_known_on_a_date() {
+        let vintages = vintages();
+        assert_eq!(
+            vintages.value(date("2021-06-01"), date("2021-06-01")),
+            Some(5)
+        );
+        assert_eq!(
+            vintages.value(date("2021-06-01"), date("2021-06-03")),
+            Some(8)
+        );
+        assert_eq!(
+            vintages.value(date("2021-06-01"), date("2021-06-04")),
+            Some(9)
+        );
+        assert_eq!(vintages.value(date("2021-06-02"), date("2021-06-01")), None);
+        assert_eq!(vintages.value(date("2021-06-01"), date("2021-05-31")), None);
+    }
+
+    #[test]
+    fn triangle_by_delay() {
+        let triangle = vintages().triangle(2);
+        let row = |d| triangle.rows[&date(d)].clone();
+        let expected: im::OrdMap<i64, i64> = vec![(0, 5), (1, 8)].into_iter().collect();
+        // Published three days later, past the maximum delay.
+        assert_eq!(row("2021-06-01"), expected);
+        let expected: im::OrdMap<i64, i64> = vec![(0, 4), (2, 7)].into_iter().collect();
+        assert_eq!(row("2021-06-02"), expected);
+        assert_eq!(triangle.max_delay(), Some(2));
+        assert_eq!(
+            triangle.to_string(),
+            "Dato;0;1;2\n2021-06-01;5;8;\n2021-06-02;4;;7\n"
+        );
+    }
+}
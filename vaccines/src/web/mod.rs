@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::table::TimeSeriesGroup;
+use crate::table::{TimeSeries, TimeSeriesGroup, Value};
 use horrorshow::prelude::*;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -9,8 +9,10 @@ struct ChartDataSet {
     label: String,
     background_color: String,
     border_color: String,
-    data: Vec<Option<i64>>,
+    data: Vec<Option<serde_json::Number>>,
     fill: String,
+    #[serde(rename = "yAxisID", skip_serializing_if = "Option::is_none")]
+    y_axis_id: Option<String>,
     border_width: u64,
     point_radius: u64,
     point_hover_radius: u64,
@@ -47,14 +49,29 @@ struct ChartScaleLabel {
     label_string: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartTicks {
+    suggested_min: f64,
+    suggested_max: f64,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ChartScale {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     stacked: bool,
     display: bool,
     scale_label: ChartScaleLabel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ticks: Option<ChartTicks>,
 }
 
+/// The y axis of lines drawn over a stacked chart, like a 7-day average. Chart.js stacks every
+/// line on the same axis, so they get their own with the same range.
+const OVERLAY_AXIS: &str = "overlay";
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ChartScales {
@@ -85,23 +102,27 @@ pub struct ChartGraph {
 }
 
 impl ChartGraph {
-    pub fn bar_plot(
+    pub fn bar_plot<V: Value>(
         id: String,
         title: String,
         x: String,
         y: String,
-        series: TimeSeriesGroup,
+        series: TimeSeriesGroup<V>,
         stacked: bool,
     ) -> ChartGraph {
         let xs = series.xs();
+        let overlaid = stacked && !series.overlay().is_empty();
 
         let colors = colorous::TURBO;
+        let count = series.len() + series.overlay().len();
         let datasets = series
             .series()
             .iter()
+            .map(|ts| (ts, false))
+            .chain(series.overlay().iter().map(|ts| (ts, true)))
             .enumerate()
-            .map(|(n, ts)| {
-                let color = colors.eval_rational(n, series.len());
+            .map(|(n, (ts, overlay))| {
+                let color = colors.eval_rational(n, count);
                 ChartDataSet {
                     label: ts
                         .tags
@@ -111,15 +132,49 @@ impl ChartGraph {
                         .join(","),
                     background_color: format!("#{:x}", color),
                     border_color: format!("#{:x}", color),
-                    data: xs.iter().map(|x| ts.data.get(x).cloned()).collect(),
-                    fill: if stacked { "start".to_string() } else { "none".to_string() },
-                    border_width: 1,
+                    data: xs
+                        .iter()
+                        .map(|x| ts.value(x)?.to_json(series.decimals()))
+                        .collect(),
+                    fill: if stacked && !overlay {
+                        "start".to_string()
+                    } else {
+                        "none".to_string()
+                    },
+                    y_axis_id: Some(OVERLAY_AXIS.to_string()).filter(|_| overlaid && overlay),
+                    border_width: if overlay { 2 } else { 1 },
                     point_radius: 0,
                     point_hover_radius: 1,
                 }
             })
             .collect();
 
+        // The range of both the stacked values and the overlay, for both axes.
+        let ticks = if overlaid {
+            let (mut min, mut max) = (0f64, 0f64);
+            for x in &xs {
+                let values = |all: &[TimeSeries<V>]| {
+                    all.iter()
+                        .filter_map(|ts| ts.value(x))
+                        .map(Value::to_f64)
+                        .collect::<Vec<_>>()
+                };
+                let stack = values(series.series());
+                min = min.min(stack.iter().filter(|v| **v < 0.0).sum());
+                max = max.max(stack.iter().filter(|v| **v > 0.0).sum());
+                for v in values(series.overlay()) {
+                    min = min.min(v);
+                    max = max.max(v);
+                }
+            }
+            Some(ChartTicks {
+                suggested_min: min,
+                suggested_max: max,
+            })
+        } else {
+            None
+        };
+
         let options = ChartOptions {
             responsive: true,
             title: ChartTitle {
@@ -136,31 +191,43 @@ impl ChartGraph {
             },
             scales: ChartScales {
                 x_axes: vec![ChartScale {
+                    id: None,
                     stacked,
                     display: true,
                     scale_label: ChartScaleLabel {
                         display: false,
                         label_string: x,
                     },
+                    ticks: None,
                 }],
-                y_axes: vec![ChartScale {
+                y_axes: std::iter::once(ChartScale {
+                    id: None,
                     stacked,
                     display: true,
                     scale_label: ChartScaleLabel {
                         display: true,
+                        label_string: y.clone(),
+                    },
+                    ticks: ticks.clone(),
+                })
+                .chain(ticks.map(|ticks| ChartScale {
+                    id: Some(OVERLAY_AXIS.to_string()),
+                    stacked: false,
+                    display: false,
+                    scale_label: ChartScaleLabel {
+                        display: false,
                         label_string: y,
                     },
-                }],
+                    ticks: Some(ticks),
+                }))
+                .collect(),
             },
         };
 
         let config = ChartConfig {
             type_: "line".to_string(),
             data: ChartData {
-                labels: xs
-                    .iter()
-                    .map(|s| s.format("%Y-%m-%d").to_string())
-                    .collect(),
+                labels: xs.iter().map(|s| series.period().label(s)).collect(),
                 datasets,
             },
             options,
@@ -169,25 +236,39 @@ impl ChartGraph {
         ChartGraph { name: id, config }
     }
 
-    pub fn bar_plot_html(
+    pub fn bar_plot_html<V: Value>(
         id: String,
         title: String,
         x: String,
         y: String,
-        series: TimeSeriesGroup,
+        series: TimeSeriesGroup<V>,
         stacked: bool
     ) -> impl horrorshow::RenderOnce {
+        let unit = series.unit().map(|unit| serde_json::to_string(unit).unwrap());
         let graph = Self::bar_plot(id.clone(), title, x, y, series, stacked);
         let json = serde_json::to_string_pretty(&graph.config).unwrap();
 
+        // Chart.js takes the tooltip text from a function, which JSON cannot hold.
+        let tooltips = match unit {
+            Some(unit) => format!(
+                "
+  config.options.tooltips.callbacks = {{
+    label: function (item, data) {{
+      return data.datasets[item.datasetIndex].label + \": \" + item.yLabel + \" \" + {};
+    }}
+  }};",
+                unit
+            ),
+            None => String::new(),
+        };
         let js = format!(
             "
 window.addEventListener(\"load\", function () {{
-  var config = {};
+  var config = {};{}
   var ctx = document.getElementById(\"{}\").getContext(\"2d\");
   window.myGraph{} = new Chart(ctx, config);
 }});",
-            json, graph.name, graph.name
+            json, tooltips, graph.name, graph.name
         );
 
         html! {